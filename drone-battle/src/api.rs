@@ -11,5 +11,19 @@ pub mod enums;
 pub mod structs;
 pub mod config;
 pub mod ai;
+pub mod decision;
+pub mod utility;
 pub mod map;
-pub mod graphics;
\ No newline at end of file
+pub mod graphics;
+pub mod arena;
+pub mod tuning;
+pub mod env;
+pub mod policy;
+pub mod mcts;
+pub mod energy;
+pub mod stuck;
+pub mod trace;
+pub mod team;
+pub mod hunt;
+pub mod search;
+pub mod bench;
@@ -1,60 +1,181 @@
+use serde::Deserialize;
+
 use crate::api::bot::BotData;
 use crate::api::enums::{Action, PlayerDirection};
 use crate::api::map::{Field, Position};
 use crate::api::map::path::Path;
 use crate::api::structs::LastObservation;
-use crate::api::config::Config;
-use crate::api::map::{query, update, logic, Coord};
+use crate::api::config::{Config, Strategy};
+use crate::api::utility::{self, UtilityConfig, Input, Score};
+use crate::api::decision::{DecisionTable, Condition, Checked, Percept, FieldQuery, Counter, RuleAction};
+use crate::api::policy::Policy;
+use crate::api::env;
+use crate::api::mcts::{self, Budget};
+use crate::api::energy::{self, EnergyModel};
+use crate::api::stuck::{self, StuckDetector, Pattern};
+use crate::api::hunt::{self, Opponents};
+use crate::api::search::Search;
+use crate::api::trace::{DecisionTrace, TraceLog, RuleTrace, Candidate};
+
+use crate::api::map::{query, update, logic, inference, Coord};
+use crate::api::map::route::{self, Route};
+use crate::api::map::{frontier, distance};
+use crate::api::map::dstar::DStarLite;
+use crate::api::map::{enemies, threat};
+use crate::api::map::pose::{self, Motion};
 use crate::api::map::update::SetType;
 
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use std::rc::Rc;
+
+/// How much a powerup is worth in a route, for each point of energy missing
+const POWERUP_VALUE_PER_ENERGY: f64 = 2.0;
+/// Farthest block, in steps, to run to when hiding from the enemies
+const RUN_RADIUS: u8 = 3;
+
 pub struct AI {
     // field
     pub field: Field,
 
     // base variables
+    decision_table: Rc<DecisionTable>,
+    utility: Rc<UtilityConfig>,
+    policy: Option<Rc<Policy>>,
+    /// Score of each behaviour in the last tick, when using the utility AI
+    pub utility_scores: Vec<Score>,
     pub current_state: BotState,
     current_action: Action,
 
     // advanced variables
-    ticks_running: i8,
-    ticks_attacking: i8,
+    ticks_running: i32,
+    ticks_attacking: i32,
+    ticks_hunting: i32,
     map_changed: bool,
+    /// The map was changed between ticks, by the messages of the team
+    pub pending_change: bool,
     c_buffer: Coord,
-    going_to_powerup: bool,
-    buffer_path: Option<Path>,
+    dir_buffer: PlayerDirection,
+    /// Energy in the last tick, 0 if dead
+    e_buffer: i32,
+    /// Learned from the energy of each tick, to plan the recharges
+    pub energy: EnergyModel,
+    stuck: StuckDetector,
+    /// Energy of the opponents, to hunt the weakened ones
+    pub opponents: Opponents,
+    /// Directions already faced while looking for an enemy heard nearby
+    search: Search,
+    route: Option<Route>,
+    /// Keeps the path to the same destination valid as the map changes, instead of planning again
+    planner: Option<DStarLite>,
 
     // for exploration
     previous_state: BotState,
     pub current_path: Option<Path>,
+    /// Frontier blocks of the last exploration
+    pub frontier: Vec<Coord>,
 
     // graphics
-    verbose: bool
+    verbose: bool,
+    /// Hides the errors as well, for headless matches
+    quiet: bool,
+
+    // tracing
+    /// Why the last action was chosen. Only filled when tracing
+    pub trace: DecisionTrace,
+    tracing: bool,
+    trace_log: Option<TraceLog>,
+    ticks: u64,
+    /// Randomness of the tree search and of the moves out of stuck spots, seeded in the arena so a match plays the same each time
+    rng: StdRng,
+    mcts_budget: Budget,
 }
 
 impl AI {
     pub fn new(config: &Config, verbose: bool) -> AI {
+        AI::build(config, verbose, false, StdRng::from_entropy())
+    }
+
+    /// Creates an AI that prints nothing, to play matches in the local arena, with the seed of the match
+    pub fn headless(config: &Config, seed: u64) -> AI {
+        AI {
+            mcts_budget: Budget::SIMULATIONS(mcts::ARENA_SIMULATIONS),
+            ..AI::build(config, false, true, StdRng::seed_from_u64(seed))
+        }
+    }
+
+    fn build(config: &Config, verbose: bool, quiet: bool, rng: StdRng) -> AI {
+        // traces go to the file, to the graphics or to the terminal
+        let trace_log = match &config.trace {
+            Some(t) if !quiet => TraceLog::create(t)
+                .map_err(|e| println!("[AI ERROR]: could not create the trace file {}: {}", t, e))
+                .ok(),
+            _ => None
+        };
         AI {
             field: Field::new(config),
+            decision_table: Rc::new(config.decision_table.clone()),
+            utility: Rc::new(config.utility.clone()),
+            policy: config.policy.clone().map(Rc::new),
+            utility_scores: Vec::new(),
             current_state: BotState::EXPLORE,
             current_action: Action::NOTHING,
             ticks_running: 0,
             ticks_attacking: 0,
+            ticks_hunting: 0,
             map_changed: false,
+            pending_change: false,
             c_buffer: Coord { x: -1, y: -1},
-            going_to_powerup: false,
+            dir_buffer: PlayerDirection::NORTH,
+            e_buffer: 0,
+            energy: EnergyModel::new(),
+            stuck: StuckDetector::new(),
+            opponents: Opponents::new(),
+            search: Search::new(),
             previous_state: BotState::NONE,
             current_path: None,
-            buffer_path: None,
-            verbose
+            frontier: Vec::new(),
+            route: None,
+            planner: None,
+            verbose,
+            quiet,
+            trace: DecisionTrace::default(),
+            tracing: !quiet && (verbose || config.graphics || trace_log.is_some()),
+            trace_log,
+            ticks: 0,
+            rng,
+            mcts_budget: Budget::TIME,
         }
     }
 
     pub fn think (&mut self, bot: BotData) -> Action {
 
         // probably dead, skipping
-        if bot.get_energy() == 0 { return Action::NOTHING; }
+        if bot.get_energy() == 0 {
+            self.e_buffer = 0;
+            return Action::NOTHING;
+        }
 
+        self.ticks += 1;
+        self.start_trace(&bot);
         self.update_field(&bot);
+        distance::forget(&self.field);
+
+        // a learned policy and the tree search choose the action directly, without states
+        let direct = match (&self.field.config.strategy, &self.policy) {
+            (Strategy::Policy, Some(policy)) => Some(policy.act(&env::encode(&self.field, &bot))),
+            (Strategy::Mcts, _) => Some(self.search_action(&bot)),
+            _ => None
+        };
+        if let Some(action) = direct {
+            self.current_action = action;
+            self.current_state = BotState::NONE;
+            self.c_buffer = Coord {x: bot.get_x(), y: bot.get_y() };
+            self.dir_buffer = bot.get_dir();
+            if self.verbose { println!("[AI] current_action: {:?}", &self.current_action) }
+            self.finish_trace();
+            return self.current_action.clone();
+        }
         // updating states
         self.previous_state = self.current_state.clone();
         self.current_state = self.generate_state(&bot);
@@ -63,34 +184,159 @@ impl AI {
         match self.current_state {
             BotState::RUN => self.do_run(&bot),
             BotState::ATTACK => self.do_attack(),
+            BotState::HUNT => self.do_hunt(&bot),
+            BotState::SEARCH => self.do_search(&bot),
             BotState::COLLECT => self.do_collect(&bot),
             BotState::EXPLORE => self.do_explore(&bot),
             BotState::RECHARGE => self.do_recharge(&bot),
             _ => {}
         }
 
+        // getting out of loops
+        let o: LastObservation = bot.get_last_observation();
+        self.stuck.push(&Coord { x: bot.get_x(), y: bot.get_y() }, &bot.get_dir(), &self.current_action, o.is_blocked);
+        if let Some(pattern) = self.stuck.check() { self.recover(&bot, pattern) }
+
         // updating another internal variables and structures
         if self.current_state != BotState::ATTACK { self.ticks_attacking = 0; }
+        // the time budget of a hunt lasts until the enemy is lost
+        if self.current_state != BotState::HUNT && !self.query(&bot, &FieldQuery::HuntTarget) { self.ticks_hunting = 0; }
         self.c_buffer = Coord {x: bot.get_x(), y: bot.get_y() };
+        self.dir_buffer = bot.get_dir();
 
         if self.verbose {
-            println!("[AI] safe_positions: {}", self.field.safe_positions.len());
+            println!("[AI] safe_positions: {} | coverage: {:.1}%", self.field.grid.safe_count(), query::coverage(&self.field));
+            println!("[AI] energy: {:.2}/tick | damage per hit: {:.1} | ticks to empty: {:?}",
+                self.energy.cost_per_tick(), self.energy.damage_per_hit, self.energy.ticks_to_empty(bot.get_energy()));
+            let (facing, chance) = query::best_facing(&self.field, &Coord { x: bot.get_x(), y: bot.get_y() });
+            println!("[AI] enemies: seen with {:.2} | best facing {:?} with {:.2}",
+                query::enemy_sees(&self.field, &Coord { x: bot.get_x(), y: bot.get_y() }), facing, chance);
             println!("[AI] map_changed: {:?} | previous_state: {:?}", &self.map_changed, &self.previous_state);
             println!("[AI] path: {:?}", match &self.current_path { Some(p) => p.to_string(), None => "{ }".to_string() });
+            if let Some(r) = &self.route {
+                println!("[AI] route: {} stops, {:.1} points/s", r.stops.len(), r.score_rate);
+            }
+            if !self.utility_scores.is_empty() {
+                println!("[AI] utility: {}", self.utility_scores.iter()
+                    .map(|s| s.to_string())
+                    .collect::<Vec<String>>()
+                    .join(" | "));
+            }
             println!("[AI] current_state: {:?} | current_action: {:?}", &self.current_state, &self.current_action);
         }
+        self.finish_trace();
+        if self.verbose && self.tracing { println!("[AI] trace: {}", self.trace.summary()) }
         self.current_action.clone()
     }
 
+    fn start_trace(&mut self, bot: &BotData) {
+        if !self.tracing { return }
+        let o: LastObservation = bot.get_last_observation();
+        let percepts: Vec<&str> = [
+            (o.is_enemy_front, "enemy_front"), (o.is_blocked, "blocked"), (o.is_steps, "steps"),
+            (o.is_breeze, "breeze"), (o.is_flash, "flash"), (o.is_treasure, "treasure"),
+            (o.is_powerup, "powerup"), (o.is_damage, "damage"), (o.is_hit, "hit"),
+        ].iter().filter(|(b, _)| *b).map(|(_, p)| *p).collect();
+
+        self.trace = DecisionTrace {
+            tick: self.ticks,
+            x: bot.get_x(),
+            y: bot.get_y(),
+            dir: bot.get_dir().to_string(),
+            energy: bot.get_energy(),
+            ..DecisionTrace::default()
+        };
+        self.trace.input("percepts", percepts.join(","));
+        if o.is_enemy_front { self.trace.input("distance_enemy_front", o.distance_enemy_front) }
+    }
+
+    fn finish_trace(&mut self) {
+        if !self.tracing { return }
+        self.trace.state = self.current_state.to_string();
+        self.trace.action = self.current_action.to_str().to_string();
+        if let Some(log) = self.trace_log.as_mut() {
+            if let Err(e) = log.write(&self.trace) {
+                println!("[AI ERROR]: could not write the trace: {}", e);
+                self.trace_log = None;
+            }
+        }
+    }
+
+    fn candidate(&mut self, kind: &str, c: &Coord, cost: f64, value: Option<f64>) {
+        if self.tracing {
+            self.trace.candidates.push(Candidate { kind: kind.to_string(), x: c.x, y: c.y, cost, value });
+        }
+    }
+
+    /// Leaves a loop: the target of the path is blacklisted, and the bot explores somewhere else
+    /// or takes a random safe step
+    fn recover(&mut self, bot: &BotData, pattern: Pattern) {
+        let c: Coord = Coord { x: bot.get_x(), y: bot.get_y() };
+        let dir: PlayerDirection = bot.get_dir();
+        if !self.quiet { println!("[AI STUCK]: {} while in {:?}", pattern.to_string(), self.current_state) }
+        if self.tracing { self.trace.recovery = Some(pattern.to_string()) }
+
+        if let Some(p) = self.current_path.take() { self.stuck.blacklist(&p.dest) }
+        self.stuck.clear();
+        match pattern {
+            Pattern::NO_PROGRESS(_) if self.current_state == BotState::EXPLORE => {
+                self.map_changed = true;    // does not reuse the last path
+                self.do_explore(bot);
+            },
+            _ => self.current_action = stuck::safe_step(&self.field, &c, &dir, &mut self.rng),
+        }
+    }
+
+    /// Gold the bot is going after, and the length of the path to it, to tell the teammates
+    pub fn claim(&self) -> Option<(Coord, f64)> {
+        if self.current_state != BotState::COLLECT { return None }
+        let stop = self.route.as_ref()?.first()?;
+        if !self.field.gold_positions.contains_key(&stop.dest) { return None }
+        Some((stop.dest.clone(), stop.size as f64))
+    }
+
+    fn search_action(&mut self, bot: &BotData) -> Action {
+        let o: LastObservation = bot.get_last_observation();
+        let c: Coord = Coord { x: bot.get_x(), y: bot.get_y() };
+        let dir: PlayerDirection = bot.get_dir();
+
+        let root = mcts::Root {
+            can_shoot: o.is_enemy_front && !query::has_wall_front(&self.field, &c, &dir, o.distance_enemy_front),
+            gold_here: o.is_treasure,
+            powerup_here: o.is_powerup,
+            coord: c,
+            dir,
+            energy: bot.get_energy(),
+            powerup_value: POWERUP_VALUE_PER_ENERGY,
+        };
+        let decision = mcts::search(&self.field, &root, &self.mcts_budget, &mut self.rng);
+        if self.verbose {
+            println!("[AI] mcts: {} simulations, {:?} with value {:.1}", decision.simulations, decision.action, decision.value);
+        }
+        decision.action
+    }
+
+    /// Updates the field with the result of an action chosen outside of the AI, as in `env::Env`
+    pub fn perceive(&mut self, bot: &BotData, last_action: &Action) {
+        self.current_action = last_action.clone();
+        if bot.get_energy() == 0 {
+            self.e_buffer = 0;
+            return;
+        }
+
+        self.update_field(bot);
+        self.c_buffer = Coord {x: bot.get_x(), y: bot.get_y() };
+        self.dir_buffer = bot.get_dir();
+    }
+
     fn update_field(&mut self, bot: &BotData) {
         let dir: PlayerDirection = bot.get_dir();
         let c: Coord = Coord { x: bot.get_x(), y: bot.get_y() };
         let mut is_empty: bool = true;
-        let mut is_danger: bool = false;
-        let mut is_wall: bool = false;
         let o: LastObservation = bot.get_last_observation();
-        self.map_changed = false;
-        self.going_to_powerup = false;
+        self.map_changed = self.field.fit(&c) | std::mem::take(&mut self.pending_change);
+        self.field.provenance.remove(&c);      // seen by the bot itself from now on
+        self.energy.observe(&self.current_action, bot.get_energy(), &o);
 
         let f_mut: &mut Field = &mut self.field;
 
@@ -99,41 +345,42 @@ impl AI {
             f_mut.set_spawn(&c);
         }
 
-        // if the bot for some reason walks into a flash and teleports, update that position as a flash
-        // ELSE to not set danger with invalid buffer on spawn
-        else if ((c.x - self.c_buffer.x) + (c.y - self.c_buffer.y)) > 3 {
-            update::set(f_mut, self.c_buffer.clone(), Position::DANGER, true);
-            self.map_changed = true;
-        }
-
-        // if the bot took some damage, set some positions as unsafe so he can get out of there
-        if o.is_damage {
-            let unsafe_positions = vec![
-                c.clone(), Coord { x: c.x - 1, y: c.y }, Coord { x: c.x - 2, y: c.y },
-                Coord {x: c.x + 1, y: c.y}, Coord {x: c.x + 2, y: c.y},
-                Coord {x: c.x, y: c.y - 1, }, Coord {x: c.x, y: c.y - 2},
-                Coord {x: c.x, y: c.y + 1, }, Coord {x: c.x, y: c.y + 2}
-            ];
-            for up in unsafe_positions {
-                update::set_unsafe(f_mut, up);
+        // compares where the last action should have taken the bot with where the server says it is
+        else {
+            let respawned = self.e_buffer == 0 || (bot.get_energy() > self.e_buffer && self.current_action != Action::GET);
+            let motion = pose::classify(
+                &self.c_buffer, &self.dir_buffer, &self.current_action, &c, &dir, o.is_blocked, respawned
+            );
+            match &motion {
+                // the bot walked into a flash and teleported
+                Motion::TELEPORT { entry } => {
+                    update::set_teleport(f_mut, entry.clone(), c.clone());
+                    self.map_changed = true;
+                },
+                Motion::RESPAWN | Motion::CORRECTION => self.map_changed = true,
+                Motion::EXPECTED | Motion::BLOCKED | Motion::IGNORED => {}
+            }
+            if self.verbose && !matches!(motion, Motion::EXPECTED) {
+                println!("[AI] motion: {:?} from {:?} to {:?} after {:?}", motion, self.c_buffer, c, self.current_action);
             }
         }
+        self.e_buffer = bot.get_energy();
 
         // FLASH | HOLE
-        if o.is_flash || o.is_breeze {
-            update::set_custom(f_mut, &c, SetType::AROUND, None, Position::DANGER);
-            is_danger = true;
+        // the inference decides which blocks around are dangerous, and which are safe
+        if inference::observe(f_mut, &c, o.is_breeze, o.is_flash) {
             self.map_changed = true;
         }
 
         // WALL
         if o.is_blocked {
+            let side = if self.current_action == Action::FRONT { dir.clone() } else { dir.opposite() };
+            f_mut.fit(&c.next(&side));
             if self.current_action == Action::FRONT {
                 update::set_custom(f_mut, &c, SetType::FRONT, Some(dir.clone()), Position::WALL);
             } else {
                 update::set_custom(f_mut, &c, SetType::BACK, Some(dir.clone()), Position::WALL);
             }
-            is_wall = true;
             self.map_changed = true;
         }
 
@@ -153,72 +400,274 @@ impl AI {
             is_empty = false;
         }
 
-        // NOT DANGER
-        if !is_danger {
-            update::set_custom(f_mut, &c, SetType::AROUND, None, Position::SAFE);
-            if !is_wall {
-                if self.current_action == Action::FRONT {
-                    update::set_custom(f_mut, &c, SetType::FRONT, Some(dir.clone()), Position::SAFE)
-                } else {
-                    update::set_custom(f_mut, &c, SetType::BACK, Some(dir.clone()), Position::SAFE)
-                }
-            }
-        }
-
         // EMPTY
         if is_empty {
             update::remove_safe(f_mut, &c);
             update::set(f_mut, c.clone(), Position::EMPTY, false);
             logic::should_something_be_here(f_mut, &c);
         }
+
+        // ENEMIES
+        // after the walls, that block their sight
+        enemies::predict(f_mut);
+        enemies::observe(f_mut, &c, &dir, &o);
+
+        // if the bot took some damage, set the lanes it came from as unsafe so he can get out of there
+        if o.is_damage {
+            threat::fired(f_mut, &c, o.shooter.as_ref());
+            if self.verbose { println!("[AI] shot from {:?}, {} unsafe blocks", o.shooter, f_mut.grid.unsafe_count()) }
+        }
     }
 
     fn generate_state(&mut self, bot: &BotData) -> BotState {
+        match self.field.config.strategy {
+            Strategy::DecisionTable => self.generate_state_from_table(bot),
+            Strategy::Utility => self.generate_state_from_utility(bot),
+            Strategy::Policy | Strategy::Mcts => self.generate_state_from_table(bot),    // not used
+        }
+    }
+
+    fn generate_state_from_table(&mut self, bot: &BotData) -> BotState {
+        // the first rule that matches gives the state
+        let table = Rc::clone(&self.decision_table);
+        for rule in table.rules.iter() {
+            let checked = self.check(bot, &rule.when);
+            let matched = checked.value;
+            if self.tracing {
+                let reason = if matched { None } else { Some(self.explain(bot, &rule.when, &checked)) };
+                self.trace.rules.push(RuleTrace { name: rule.name.clone(), matched, reason });
+            }
+            if matched {
+                for a in rule.then.iter() { self.run_rule_action(a); }
+                return rule.state.clone();
+            }
+        }
+        // the last rule always matches, so it should never get here
+        BotState::EXPLORE
+    }
+
+    fn generate_state_from_utility(&mut self, bot: &BotData) -> BotState {
+        let utility = Rc::clone(&self.utility);
+        let mut best: Option<(BotState, f64)> = None;
+
+        self.utility_scores.clear();
+        for b in utility.behaviours.iter() {
+            let mut score = b.weight;
+            let mut considerations: Vec<(Input, f64)> = Vec::new();
+            for c in b.considerations.iter() {
+                if score <= 0.0 { break }       // no need to read the other inputs
+                let value = c.curve.eval(self.utility_input(bot, &c.input));
+                considerations.push((c.input.clone(), value));
+                score *= value;
+            }
+            let state = b.behaviour.state();
+            if state == self.previous_state { score += utility.hysteresis }
+
+            self.utility_scores.push(Score { behaviour: b.behaviour.clone(), score, considerations });
+            if best.as_ref().is_none_or(|(_, s)| score > *s) { best = Some((state, score)) }
+        }
+        let state = best.map(|(s, _)| s).unwrap_or(BotState::EXPLORE);
+        if self.tracing {
+            for s in self.utility_scores.iter() {
+                let matched = s.behaviour.state() == state;
+                self.trace.rules.push(RuleTrace { name: s.behaviour.to_string(), matched, reason: Some(s.to_string()) });
+            }
+        }
+
+        // keeps following the same escape path for some ticks, like the decision table does
+        if state == BotState::RUN {
+            let ticks_running = self.field.config.params.ticks_running.round() as i32;
+            self.ticks_running = if self.previous_state == BotState::RUN { (self.ticks_running - 1).max(0) } else { ticks_running };
+        }
+        state
+    }
+
+    fn utility_input(&mut self, bot: &BotData, input: &Input) -> f64 {
+        let o: LastObservation = bot.get_last_observation();
+        let c: Coord = Coord { x: bot.get_x(), y: bot.get_y() };
+        let dir: PlayerDirection = bot.get_dir();
+
+        let distance = |p: &Path| (p.size as f64 / utility::MAX_DISTANCE).min(1.0);
+        let bool_input = |b: bool| if b { 1.0 } else { 0.0 };
+
+        let value = match input {
+            Input::Energy => bot.get_energy() as f64 / 100.0,
+            Input::GoldRoute => bool_input(o.is_treasure || self.query(bot, &FieldQuery::GoldRoute)),
+            Input::GoldDistance => {
+                if o.is_treasure { 0.0 }
+                else {
+                    route::closest(&self.field, &c, &dir, powerup_value(bot.get_energy())).as_ref().map_or(1.0, distance)
+                }
+            },
+            Input::PowerupDistance => {
+                if o.is_powerup { 0.0 }
+                else { query::has_powerup_to_collect(&self.field, &c, &dir).as_ref().map_or(1.0, distance) }
+            },
+            Input::EnemyFront => bool_input(o.is_enemy_front && !self.query(bot, &FieldQuery::WallFront)),
+            Input::Threat => {
+                if o.is_damage { 1.0 }
+                else if o.is_enemy_front { 0.7 }
+                else if o.is_steps { 0.5 }
+                else { 0.0 }
+            },
+            Input::TicksAttacking => (self.ticks_attacking as f64 / utility::MAX_TICKS_ATTACKING).min(1.0),
+            Input::SafeBlocks => (self.field.grid.safe_count() as f64 / utility::MAX_SAFE_BLOCKS).min(1.0),
+            Input::HuntTarget => bool_input(self.query(bot, &FieldQuery::HuntTarget)),
+            Input::TicksHunting => (self.ticks_hunting as f64 / utility::MAX_TICKS_HUNTING).min(1.0),
+            Input::Steps => bool_input(o.is_steps && !o.is_enemy_front),
+        };
+        if self.tracing { self.trace.input(&format!("{:?}", input), format!("{:.2}", value)) }
+        value
+    }
+
+    /// Checks if a condition of the decision table is true, keeping the value of each part that was checked
+    fn check(&mut self, bot: &BotData, cond: &Condition) -> Checked {
+        let o: LastObservation = bot.get_last_observation();
+        let value = match cond {
+            Condition::All(v) | Condition::Any(v) => {
+                // stops at the first part that decides, as `all` and `any` do
+                let decides = matches!(cond, Condition::Any(_));
+                let mut parts: Vec<Checked> = Vec::new();
+                for c in v.iter() {
+                    parts.push(self.check(bot, c));
+                    if parts.last().unwrap().value == decides { return Checked { value: decides, parts } }
+                }
+                return Checked { value: !decides, parts }
+            },
+            Condition::Not(c) => {
+                let part = self.check(bot, c);
+                return Checked { value: !part.value, parts: vec![part] }
+            },
+            Condition::Always => true,
+            Condition::Energy(cmp) => cmp.check(bot.get_energy(), &self.field.config.params),
+            Condition::Counter(cmp) => cmp.check(self.counter(&cmp.name), &self.field.config.params),
+            Condition::Query(q) => self.query(bot, q),
+            Condition::Percept(p) => match p {
+                Percept::EnemyFront => o.is_enemy_front,
+                Percept::Blocked => o.is_blocked,
+                Percept::Steps => o.is_steps,
+                Percept::Breeze => o.is_breeze,
+                Percept::Flash => o.is_flash,
+                Percept::Treasure => o.is_treasure,
+                Percept::Powerup => o.is_powerup,
+                Percept::Damage => o.is_damage,
+                Percept::Hit => o.is_hit,
+            }
+        };
+        Checked { value, parts: Vec::new() }
+    }
+
+    fn query(&mut self, bot: &BotData, q: &FieldQuery) -> bool {
+        let result = self.answer(bot, q);
+        if self.tracing { self.trace.input(&format!("{:?}", q), result) }
+        result
+    }
+
+    fn answer(&mut self, bot: &BotData, q: &FieldQuery) -> bool {
         let o: LastObservation = bot.get_last_observation();
         let e: i32 = bot.get_energy();
         let c: Coord = Coord { x: bot.get_x(), y: bot.get_y() };
         let dir: PlayerDirection = bot.get_dir();
         let f: &Field = &self.field;
 
-        // if there is gold, collect it right now
-        if o.is_treasure { return BotState::COLLECT }
+        match q {
+            FieldQuery::WallFront => query::has_wall_front(f, &c, &dir, o.distance_enemy_front),
+            FieldQuery::HasGold => query::has_gold(f),
+            FieldQuery::HasPowerup => query::has_powerup(f),
+            FieldQuery::RechargeReady => energy::plan(f, &c, &dir, e, &self.energy).is_some_and(|p| p.is_ready()),
+            FieldQuery::RechargeDue => energy::plan(f, &c, &dir, e, &self.energy)
+                .is_some_and(|p| self.energy.recharge_due(e, &p)),
+            FieldQuery::HuntTarget => {
+                self.opponents.weakened(f.config.params.hunt_energy).is_some() && hunt::target(f, &c).is_some()
+            },
+            FieldQuery::GoldRoute => route::closest(f, &c, &dir, powerup_value(e)).is_some(),
+        }
+    }
 
-        // if there is a powerup and not full health, get it too
-        if o.is_powerup && e <= 70 { return BotState::RECHARGE }
+    /// Plans the route again if the targets changed, or when starting to collect. If only the map changed,
+    /// the stops are the same, and the path to the first one is repaired
+    fn update_route(&mut self, bot: &BotData) {
+        let e: i32 = bot.get_energy();
+        let c: Coord = Coord { x: bot.get_x(), y: bot.get_y() };
+        let dir: PlayerDirection = bot.get_dir();
 
-        // it has to run at least 5 times
-        if self.ticks_running > 0 {
-            self.ticks_running -= 1;
-            return BotState::RUN;
+        let outdated = self.previous_state != BotState::COLLECT || self.route.as_ref().is_none_or(|r| r.is_outdated(&self.field));
+        if !outdated && self.map_changed {
+            let dest = self.route.as_ref().and_then(|r| r.first()).map(|p| p.dest.clone());
+            if let Some(p) = dest.and_then(|d| self.repair(&c, &dir, &d)) {
+                self.route.as_mut().unwrap().stops[0] = p;
+                self.current_path = None;      // forces the repaired path to be used
+                return
+            }
         }
+        if outdated || self.map_changed {
+            self.route = route::plan(&self.field, &c, &dir, powerup_value(e));
+            self.current_path = None;      // forces the new route to be used
+        }
+    }
 
-        // checking ATTACK
-        if o.is_enemy_front &&
-            self.ticks_attacking < 10 &&
-            e > 30
-            && !query::has_wall_front(f, &c, &dir, o.distance_enemy_front) {
-
-            return BotState::ATTACK
+    /// Path to `dest` from the pose of the bot, searched again only where the map changed, if it is the same
+    /// destination as the last time
+    fn repair(&mut self, c: &Coord, dir: &PlayerDirection, dest: &Coord) -> Option<Path> {
+        if self.planner.as_ref().is_none_or(|p| p.goal() != dest) {
+            self.planner = Some(DStarLite::new(&self.field, dest, c, dir));
         }
+        let path = self.planner.as_mut()?.path(&self.field, c, dir);
+        if path.is_some() { self.trace.repaired_path = true }
+        path
+    }
 
-        // checking RUN
-        if (o.is_damage && !o.is_enemy_front) || (
-                (o.is_enemy_front || o.is_steps) && e < 30) {
-            self.ticks_running = 5;
-            return BotState::RUN;
+    /// Tells why a condition of the decision table is false, with the values that were compared.
+    /// Which parts failed comes from the check, so no query is answered twice
+    fn explain(&mut self, bot: &BotData, cond: &Condition, checked: &Checked) -> String {
+        match cond {
+            Condition::All(v) => match v.iter().zip(checked.parts.iter()).find(|(_, p)| !p.value) {
+                Some((c, p)) => self.explain(bot, c, p),
+                None => "all matched".to_string(),
+            },
+            Condition::Any(v) => v.iter().zip(checked.parts.iter())
+                .map(|(c, p)| self.explain(bot, c, p))
+                .collect::<Vec<String>>()
+                .join(" and "),
+            Condition::Not(c) => format!("not: {}", self.describe(bot, c)),
+            c => format!("{} is false", self.describe(bot, c)),
         }
+    }
 
-        // checking RECHARGE
-        if e <= 80 { return BotState::RECHARGE }
+    fn describe(&mut self, bot: &BotData, cond: &Condition) -> String {
+        let params = &self.field.config.params;
+        match cond {
+            Condition::Always => "always".to_string(),
+            Condition::All(_) => "all conditions".to_string(),
+            Condition::Any(_) => "some condition".to_string(),
+            Condition::Not(c) => format!("not ({})", self.describe(bot, c)),
+            Condition::Percept(p) => format!("percept {:?}", p),
+            Condition::Energy(cmp) => format!("energy {} {} {}", bot.get_energy(), cmp.op.symbol(), cmp.value.get(params)),
+            Condition::Counter(cmp) => format!("{:?} {} {} {}", cmp.name, self.counter(&cmp.name), cmp.op.symbol(), cmp.value.get(params)),
+            Condition::Query(q) => format!("query {:?}", q),
+        }
+    }
 
-        // checking collect
-        let path_to_gold = query::has_gold_to_collect(f, &c, &dir);
-        if query::has_gold(f) && path_to_gold.is_some() {
-            self.buffer_path = path_to_gold;
-            return BotState::COLLECT;
+    fn counter(&self, c: &Counter) -> i32 {
+        match c {
+            Counter::TicksRunning => self.ticks_running,
+            Counter::TicksAttacking => self.ticks_attacking,
+            Counter::TicksHunting => self.ticks_hunting,
         }
-        // checking explore
-        BotState::EXPLORE
+    }
+
+    fn run_rule_action(&mut self, a: &RuleAction) {
+        let (change, add) = match a {
+            RuleAction::Set(change) => (change, false),
+            RuleAction::Add(change) => (change, true),
+        };
+        let value = change.value(&self.field.config.params);
+        let counter = match change.counter {
+            Counter::TicksRunning => &mut self.ticks_running,
+            Counter::TicksAttacking => &mut self.ticks_attacking,
+            Counter::TicksHunting => &mut self.ticks_hunting,
+        };
+        if add { *counter += value } else { *counter = value }
     }
 
     fn do_attack(&mut self) {
@@ -226,6 +675,66 @@ impl AI {
         self.current_action = Action::SHOOT;
     }
 
+    /// Goes after the enemy nearby, out of the sight of the others, and shoots it
+    fn do_hunt(&mut self, bot: &BotData) {
+        let o: LastObservation = bot.get_last_observation();
+        let dir: PlayerDirection = bot.get_dir();
+        let c: Coord = Coord { x: bot.get_x(), y: bot.get_y() };
+        self.ticks_hunting += 1;
+
+        // once it is in front, or was hit, keeps shooting
+        let in_sight = o.is_enemy_front && !query::has_wall_front(&self.field, &c, &dir, o.distance_enemy_front);
+        if in_sight || o.is_hit {
+            self.current_action = Action::SHOOT;
+            return
+        }
+
+        // heard it, so it is right next to the bot
+        if o.is_steps {
+            self.do_search(bot);
+            return
+        }
+
+        // faces where the enemy most probably is, if it is close enough to shoot
+        let (facing, chance) = query::best_facing(&self.field, &c);
+        if chance >= hunt::SHOOT_BELIEF {
+            self.current_action = if facing == dir { Action::SHOOT } else { hunt::turn_to(&dir, &facing) };
+            return
+        }
+
+        let f: &Field = &self.field;
+        let target = hunt::target(f, &c);
+        let path = target.as_ref().and_then(|(t, _)| {
+            logic::a_star_weighted(f, &c, &dir, t, |b| hunt::COVER_COST * query::enemy_sees(f, b))
+        });
+        match (target, path) {
+            (Some((t, p)), Some(path)) => {
+                self.candidate("enemy", &t, path.size as f64, Some(p));
+                self.current_action = path.get_first();
+                self.current_path = Some(path);
+            },
+            _ => self.do_explore(bot),
+        }
+    }
+
+    /// Turns to the next direction where the enemy heard may be. The attack rule takes over once it is in front
+    fn do_search(&mut self, bot: &BotData) {
+        let dir: PlayerDirection = bot.get_dir();
+        let c: Coord = Coord { x: bot.get_x(), y: bot.get_y() };
+        if !matches!(self.previous_state, BotState::SEARCH | BotState::HUNT) { self.search.clear() }
+
+        self.search.check(&c, &dir);
+        let next = self.search.next(&self.field, &c, &dir);
+        if self.tracing {
+            let checked: Vec<String> = self.search.checked().iter().map(|d| d.to_string()).collect();
+            self.trace.input("checked", checked.join(","));
+        }
+        self.current_action = match next {
+            Some(d) => hunt::turn_to(&dir, &d),
+            None => Action::NOTHING,        // walls all around
+        };
+    }
+
     fn do_run(&mut self, bot: &BotData) {
         let o: LastObservation = bot.get_last_observation();
         let dir: PlayerDirection = bot.get_dir();
@@ -235,6 +744,7 @@ impl AI {
         // keep running ?
         if let Some(p) = &self.current_path {
             if self.previous_state == BotState::RUN && self.ticks_running > 0 && p.size > 1 {
+                self.trace.cached_path = true;
                 self.current_path.as_mut().unwrap().pop_first_action();
                 self.current_action =  self.current_path.as_ref().unwrap().get_first();
                 return
@@ -248,10 +758,13 @@ impl AI {
         }
 
         // first and third case
-        let mut area: Vec<Coord> = c.coords_5x2_sides(&dir);
-        area.retain(|c| {
-            query::get(f, c) != Position::WALL && query::get(f, c) != Position::DANGER
-        });
+        // blocks nearby that no enemy sees, or any block nearby if there is no cover
+        let threats: Vec<Coord> = threat::sources(f, &c, &dir, &o);
+        let mut area: Vec<Coord> = query::cover(f, &c, RUN_RADIUS, &threats);
+        if area.is_empty() { area = query::cover(f, &c, RUN_RADIUS, &[]) }
+        area.retain(|a| *a != c);
+        // out of the firing lanes, if there is somewhere to go
+        if area.iter().any(|c| !query::is_unsafe(f, c)) { area.retain(|c| !query::is_unsafe(f, c)) }
 
         let temp_path = logic::best_of_paths(f, &c, &dir, area, false);
 
@@ -264,23 +777,36 @@ impl AI {
     }
 
     fn do_collect(&mut self, bot: &BotData) {
-        if bot.get_last_observation().is_treasure {
+        // routes may stop at powerups too
+        let o: LastObservation = bot.get_last_observation();
+        if o.is_treasure || (o.is_powerup && bot.get_energy() < 100) {
             self.current_action = Action::GET;
             return;
         }
 
+        // the route is kept while the targets are the same
+        self.update_route(bot);
+
         // buffering from last move
         if let Some(cp) = &self.current_path {
             if self.previous_state == BotState::COLLECT && !self.map_changed && cp.size > 1 {
+                self.trace.cached_path = true;
                 self.current_path.as_mut().unwrap().pop_first_action();
                 self.current_action = self.current_path.as_ref().unwrap().get_first();
                 return;
             }
         }
 
-        // geting from buffer, from has_gold_to_collect
-        if let Some(bp) = &self.buffer_path {
-            self.current_action = bp.get_first();
+        // getting the path to the first stop of the route
+        if self.tracing {
+            let stops: Vec<(Coord, f64)> = self.route.iter().flat_map(|r| r.stops.iter())
+                .scan(0.0, |cost, p| { *cost += p.size as f64; Some((p.dest.clone(), *cost)) })
+                .collect();
+            for (dest, cost) in stops.iter() { self.candidate("stop", dest, *cost, None) }
+        }
+        if let Some(p) = self.route.as_ref().and_then(|r| r.first()) {
+            self.current_action = p.get_first();
+            self.current_path = Some(p.clone());
         } else {
             self.current_action = Action::NOTHING;  // error?
         }
@@ -296,68 +822,68 @@ impl AI {
             return;
         }
 
-        // checking for a powerup to run to
-        let path_to_powerup_to_collect = query::has_powerup_to_collect(f, &c, &dir);
-        if path_to_powerup_to_collect.is_some() && !self.going_to_powerup {
-            self.map_changed = true;    // force to reload the buffer (?)
-        }
-
-        // using cached moves
-        if let Some(cp) = &self.current_path {
-            if self.previous_state == BotState::RECHARGE && !self.map_changed && cp.size > 1 {
-                self.current_path.as_mut().unwrap().pop_first_action();
-                self.current_action = self.current_path.as_ref().unwrap().get_first();
-                return;
-            }
-        }
-
-        // going to some powerup that is/will be ready
-        if let Some(pc) = path_to_powerup_to_collect {
-            self.going_to_powerup = true;
-            self.current_path = Some(pc);
-            self.current_action = self.current_path.as_ref().unwrap().get_first();
-            return;
-        }
+        // going to the powerup that will be ready the soonest, or waiting to arrive as it spawns
+        match energy::plan(f, &c, &dir, bot.get_energy(), &self.energy) {
+            Some(mut plan) => {
+                // without the energy to wait, leaves now, as arriving early is better than not arriving
+                let loiter = plan.in_time && plan.wait > energy::LOITER_TICKS && !query::is_unsafe(f, &c);
+                // the same powerup as in the last tick: the path to it is repaired where the map changed
+                let same = self.previous_state == BotState::RECHARGE
+                    && self.current_path.as_ref().is_some_and(|p| p.dest == plan.target);
+                if same {
+                    if let Some(p) = self.repair(&c, &dir, &plan.target) { plan.path = p }
+                }
 
-        // searching for some powerup close by to explore
-        if query::has_powerup(f) {
-            if let Some(c_powerup) = logic::closest_powerup(f, &c, &dir) {
-                if let Some(dest) = logic::best_block_using_midpoint(f, &c, &dir, &c_powerup) {
-                    self.current_action = dest.get_first();
-                    self.going_to_powerup = false;
-                } else {
-                    println!("[AI ERROR]: cannot find close block to explore while in need to recharge");
-                    self.do_explore(bot);
+                self.candidate("powerup", &plan.target, plan.ticks as f64, None);
+                self.current_action = if loiter { Action::NOTHING } else { plan.path.get_first() };
+                if self.verbose {
+                    println!("[AI] recharge: powerup at {:?} in {} ticks, waiting {}", plan.target, plan.ticks, plan.wait);
                 }
-            } else {
-                println!("[AI ERROR]: recharge bug");
+                self.current_path = Some(plan.path);
+            },
+            None => {
+                if !self.quiet { println!("[AI INFO]: while recharging, no powerup to collect") }
                 self.do_explore(bot);
             }
         }
-        else {
-            println!("[AI INFO]: while recharging, no powerup to collect or error");
-            self.do_explore(bot);
-        }
     }
 
     fn do_explore(&mut self, bot: &BotData) {
+        let c: Coord = Coord { x: bot.get_x(), y: bot.get_y() };
+        let dir: PlayerDirection = bot.get_dir();
+
         // buffering from last move
         if let Some(cp) = &self.current_path {
             if self.previous_state == BotState::EXPLORE && !self.map_changed && cp.size > 1 {
                 if self.verbose {println!("[AI]: (explore) buffering from last move");}
+                self.trace.cached_path = true;
                 self.current_path.as_mut().unwrap().pop_first_action();
                 self.current_action = self.current_path.as_ref().unwrap().get_first();
                 return;
             }
+
+            // the map changed, but the block is still worth exploring: only the path to it is repaired
+            let dest = cp.dest.clone();
+            let same = self.previous_state == BotState::EXPLORE && cp.size > 1
+                && query::is_safe(&self.field, &dest) && !self.stuck.is_blacklisted(&dest);
+            if let Some(p) = same.then(|| self.repair(&c, &dir, &dest)).flatten() {
+                if self.verbose {println!("[AI]: (explore) path repaired");}
+                self.current_action = p.get_first();
+                self.current_path = Some(p);
+                return;
+            }
         }
-        let f: &mut Field = &mut self.field;
-        let c: Coord = Coord { x: bot.get_x(), y: bot.get_y() };
-        let dir: PlayerDirection = bot.get_dir();
+        let f: &Field = &self.field;
 
+        let mut clusters = frontier::clusters(f);
+        for cl in clusters.iter_mut() { cl.blocks.retain(|b| !self.stuck.is_blacklisted(b)) }
+        clusters.retain(|cl| !cl.blocks.is_empty());
+        self.frontier = clusters.iter().flat_map(|cl| cl.blocks.iter().cloned()).collect();
 
-        let midpoint: Coord = logic::gold_midpoint(f);
+        let (path, targets) = frontier::best_path(f, &c, &dir, &clusters);
+        for t in targets.iter() { self.candidate("frontier", &t.coord, t.cost, Some(t.value)) }
 
-        if let Some(p) = logic::best_block_using_midpoint(f, &c, &dir, &midpoint) {
+        if let Some(p) = path {
             self.current_action = p.get_first();
             self.current_path = Some(p);
 
@@ -367,13 +893,20 @@ impl AI {
     }
 }
 
-#[derive(Eq, PartialEq, Clone, Debug)]
+/// Value of the powerups in a route, as it is worth more the less energy the bot has
+fn powerup_value(energy: i32) -> Option<f64> {
+    if energy < 100 { Some((100 - energy) as f64 * POWERUP_VALUE_PER_ENERGY) } else { None }
+}
+
+#[derive(Eq, PartialEq, Clone, Debug, Deserialize)]
 pub enum BotState {
     RUN,
     ATTACK,
     COLLECT,
     EXPLORE,
     RECHARGE,
+    HUNT,
+    SEARCH,
     NONE
 }
 
@@ -385,6 +918,8 @@ impl BotState {
             BotState::ATTACK => "ATTACK".to_string(),
             BotState::COLLECT => "COLLECT".to_string(),
             BotState::RECHARGE => "RECHARGE".to_string(),
+            BotState::HUNT => "HUNT".to_string(),
+            BotState::SEARCH => "SEARCH".to_string(),
             BotState::NONE => "NONE".to_string(),
         }
     }
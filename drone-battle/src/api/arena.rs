@@ -0,0 +1,381 @@
+use crate::api::ai::AI;
+use crate::api::bot::BotData;
+use crate::api::config::Config;
+use crate::api::enums::{Action, PlayerDirection};
+use crate::api::map::{Coord, Field, Position};
+use crate::api::map::update;
+use crate::api::structs::{Color, LastObservation, Scoreboard};
+use crate::api::hunt;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rand::seq::SliceRandom;
+use std::time::Duration;
+
+// rules of the local arena. They are close to the ones of the game server, but not the same
+const GOLD_SCORE: i64 = 1000;
+const POWERUP_ENERGY: i32 = 20;
+const MAX_ENERGY: i32 = 100;
+const SHOT_DAMAGE: i32 = 10;
+const HIT_SCORE: i64 = 10;
+const DEATH_SCORE: i64 = -500;
+const RESPAWN_TICKS: u32 = 30;
+const VIEW_RANGE: i16 = 10;
+
+// generation of the map
+const WALL_RATIO: f64 = 0.12;
+const HOLE_RATIO: f64 = 0.02;
+const TELEPORT_RATIO: f64 = 0.01;
+const GOLDS: usize = 10;
+const POWERUPS: usize = 5;
+const OPPONENTS: usize = 3;
+
+#[derive(Clone, Debug, PartialEq)]
+enum Cell {
+    FLOOR,
+    WALL,
+    HOLE,
+    // teleport, with its exit
+    TELEPORT(Coord),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Script {
+    // the bot being tested
+    PLAYER,
+    // walks around, and shoots what is in front of it
+    WANDERER,
+    // wanders as well, but turns to the bot when in line with it
+    HUNTER,
+}
+
+struct Player {
+    coord: Coord,
+    dir: PlayerDirection,
+    energy: i32,
+    score: i64,
+    dead_ticks: u32,
+    script: Script,
+}
+
+struct Item {
+    coord: Coord,
+    is_gold: bool,
+    // time since collected
+    timer: Duration,
+}
+
+/// Summary of a match in the local arena
+#[derive(Clone, Debug)]
+pub struct MatchResult {
+    pub score: i64,
+    pub golds: u32,
+    pub deaths: u32,
+}
+
+/// A headless copy of the game, where each `step` is one tick and the same seed gives the same match
+pub struct Arena {
+    width: i16,
+    height: i16,
+    cells: Vec<Cell>,
+    items: Vec<Item>,
+    // the first one is the bot, the others are scripted opponents
+    players: Vec<Player>,
+    observation: LastObservation,
+    rng: StdRng,
+    tick: Duration,
+    spawn_timer: Duration,
+    golds: u32,
+    deaths: u32,
+}
+
+impl Arena {
+    pub fn new(config: &Config, seed: u64) -> Arena {
+        let mut arena = Arena {
+            width: config.map_width,
+            height: config.map_height,
+            cells: vec![Cell::FLOOR; (config.map_width as usize) * (config.map_height as usize)],
+            items: Vec::new(),
+            players: Vec::new(),
+            observation: LastObservation::new(),
+            rng: StdRng::seed_from_u64(seed),
+            tick: config.normal_timer,
+            spawn_timer: config.spawn_timer,
+            golds: 0,
+            deaths: 0,
+        };
+
+        for i in 0..arena.cells.len() {
+            let r: f64 = arena.rng.gen();
+            if r < WALL_RATIO { arena.cells[i] = Cell::WALL }
+            else if r < WALL_RATIO + HOLE_RATIO { arena.cells[i] = Cell::HOLE }
+        }
+        let teleports = (arena.cells.len() as f64 * TELEPORT_RATIO) as usize;
+        for _ in 0..teleports {
+            if let (Some(entry), Some(exit)) = (arena.free_coord(), arena.free_coord()) {
+                if entry != exit { arena.set(&entry, Cell::TELEPORT(exit)) }
+            }
+        }
+
+        // a small map may not have floor for all of them
+        for i in 0..(GOLDS + POWERUPS) {
+            if let Some(coord) = arena.free_coord() {
+                let spawn_timer = arena.spawn_timer;
+                arena.items.push(Item { coord, is_gold: i < GOLDS, timer: spawn_timer });
+            }
+        }
+
+        for i in 0..=OPPONENTS {
+            let script = match i {
+                0 => Script::PLAYER,
+                i if i % 2 == 0 => Script::HUNTER,
+                _ => Script::WANDERER,
+            };
+            let coord = arena.spawn_coord();
+            let dir = arena.random_dir();
+            arena.players.push(Player { coord, dir, energy: MAX_ENERGY, score: 0, dead_ticks: 0, script });
+        }
+        arena.observe();
+        arena
+    }
+
+    /// Current state of the bot, as the server would tell it
+    pub fn bot_data(&self) -> BotData {
+        let p = &self.players[0];
+        BotData::new(p.coord.x, p.coord.y, p.dir.clone(), p.energy, self.observation.clone(), p.score)
+    }
+
+    /// The opponents, as the scoreboard of the server would tell them
+    pub fn scoreboard(&self) -> Vec<Scoreboard> {
+        self.players.iter().enumerate().skip(1).map(|(i, p)| Scoreboard {
+            name: format!("opponent{}", i),
+            connected: true,
+            score: p.score,
+            energy: p.energy,
+            color: Color { r: 0, g: 0, b: 0, a: 0 },
+        }).collect()
+    }
+
+    /// Tells the whole map to a field, as if every block was explored
+    pub fn reveal(&self, f: &mut Field) {
+        for x in 0..self.width {
+            for y in 0..self.height {
+                let c = Coord { x, y };
+                match self.get(&c) {
+                    Cell::FLOOR => update::set(f, c, Position::EMPTY, true),
+                    Cell::WALL => update::set(f, c, Position::WALL, true),
+                    Cell::HOLE => update::set(f, c, Position::HOLE, true),
+                    Cell::TELEPORT(exit) => update::set_teleport(f, c, exit),
+                }
+            }
+        }
+    }
+
+    pub fn result(&self) -> MatchResult {
+        MatchResult { score: self.players[0].score, golds: self.golds, deaths: self.deaths }
+    }
+
+    /// Runs one tick: the action of the bot, then the ones of the opponents
+    pub fn step(&mut self, action: &Action) {
+        self.observation = LastObservation::new();
+
+        if self.players[0].dead_ticks == 0 { self.act(0, action) }
+        for i in 1..self.players.len() {
+            if self.players[i].dead_ticks > 0 { continue }
+            let a = self.script(i);
+            self.act(i, &a);
+        }
+
+        for item in self.items.iter_mut() { item.timer += self.tick }
+        for i in 0..self.players.len() {
+            if self.players[i].dead_ticks == 0 { continue }
+            self.players[i].dead_ticks -= 1;
+            if self.players[i].dead_ticks == 0 {
+                let coord = self.spawn_coord();
+                let dir = self.random_dir();
+                let p = &mut self.players[i];
+                p.coord = coord;
+                p.dir = dir;
+                p.energy = MAX_ENERGY;
+            }
+        }
+        self.observe();
+    }
+
+    fn act(&mut self, i: usize, action: &Action) {
+        match action {
+            Action::FRONT | Action::BACK => {
+                let p = &self.players[i];
+                let dir = if *action == Action::FRONT { p.dir.clone() } else { p.dir.opposite() };
+                let next = p.coord.next(&dir);
+                match self.get(&next) {
+                    Cell::WALL => { if i == 0 { self.observation.is_blocked = true } },
+                    _ if self.player_at(&next).is_some() => { if i == 0 { self.observation.is_blocked = true } },
+                    Cell::HOLE => self.die(i),
+                    Cell::TELEPORT(exit) => self.players[i].coord = exit,
+                    Cell::FLOOR => self.players[i].coord = next,
+                }
+            },
+            Action::LEFT => self.players[i].dir = self.players[i].dir.left(),
+            Action::RIGHT => self.players[i].dir = self.players[i].dir.right(),
+            Action::GET => {
+                let spawn_timer = self.spawn_timer;
+                let p = &mut self.players[i];
+                if let Some(item) = self.items.iter_mut().find(|it| it.coord == p.coord && it.timer >= spawn_timer) {
+                    item.timer = Duration::from_secs(0);
+                    if item.is_gold {
+                        p.score += GOLD_SCORE;
+                        if i == 0 { self.golds += 1 }
+                    } else {
+                        p.energy = (p.energy + POWERUP_ENERGY).min(MAX_ENERGY);
+                    }
+                }
+            },
+            Action::SHOOT => {
+                if let Some((j, _)) = self.in_sight(i) {
+                    self.players[i].score += HIT_SCORE;
+                    self.players[j].energy -= SHOT_DAMAGE;
+                    if i == 0 { self.observation.is_hit = true }
+                    if j == 0 {
+                        self.observation.is_damage = true;
+                        self.observation.shooter = Some(self.players[i].coord.clone());
+                    }
+                    if self.players[j].energy <= 0 { self.die(j) }
+                }
+            },
+            Action::NOTHING => {}
+        }
+    }
+
+    fn die(&mut self, i: usize) {
+        let p = &mut self.players[i];
+        p.energy = 0;
+        p.score += DEATH_SCORE;
+        p.dead_ticks = RESPAWN_TICKS;
+        if i == 0 { self.deaths += 1 }
+    }
+
+    fn script(&mut self, i: usize) -> Action {
+        if self.in_sight(i).is_some_and(|(j, _)| j == 0) { return Action::SHOOT }
+
+        let p = &self.players[i];
+        let bot = &self.players[0];
+        if p.script == Script::HUNTER && bot.dead_ticks == 0 {
+            let facing = if p.coord.x == bot.coord.x {
+                if bot.coord.y < p.coord.y { Some(PlayerDirection::NORTH) } else { Some(PlayerDirection::SOUTH) }
+            } else if p.coord.y == bot.coord.y {
+                if bot.coord.x < p.coord.x { Some(PlayerDirection::WEST) } else { Some(PlayerDirection::EAST) }
+            } else { None };
+            let close = p.coord.manhattan(&bot.coord) <= VIEW_RANGE as u16;
+            if close && facing.is_some_and(|d| d != p.dir) { return Action::RIGHT }
+        }
+
+        let front = p.coord.next(&p.dir);
+        let free = self.get(&front) == Cell::FLOOR && self.player_at(&front).is_none();
+        if free && self.rng.gen_bool(0.8) { Action::FRONT }
+        else if self.rng.gen_bool(0.5) { Action::LEFT }
+        else { Action::RIGHT }
+    }
+
+    /// Sensors of the bot, after everyone moved
+    fn observe(&mut self) {
+        let p = &self.players[0];
+        if p.dead_ticks > 0 { return }
+        let c = p.coord.clone();
+
+        for n in c.around() {
+            match self.get(&n) {
+                Cell::HOLE => self.observation.is_breeze = true,
+                Cell::TELEPORT(_) => self.observation.is_flash = true,
+                _ => {}
+            }
+            if self.player_at(&n).is_some() { self.observation.is_steps = true }
+        }
+
+        for item in self.items.iter() {
+            if item.coord == c && item.timer >= self.spawn_timer {
+                if item.is_gold { self.observation.is_treasure = true } else { self.observation.is_powerup = true }
+            }
+        }
+
+        if let Some((_, distance)) = self.in_sight(0) {
+            self.observation.is_enemy_front = true;
+            self.observation.distance_enemy_front = distance;
+        }
+    }
+
+    /// First player in front of player `i`, and its distance, if no wall is between them
+    fn in_sight(&self, i: usize) -> Option<(usize, i16)> {
+        let p = &self.players[i];
+        let mut c = p.coord.clone();
+        for distance in 1..=VIEW_RANGE {
+            c = c.next(&p.dir);
+            if self.get(&c) == Cell::WALL { return None }
+            if let Some(j) = self.player_at(&c) { return Some((j, distance)) }
+        }
+        None
+    }
+
+    fn player_at(&self, c: &Coord) -> Option<usize> {
+        self.players.iter().position(|p| p.dead_ticks == 0 && p.coord == *c)
+    }
+
+    fn get(&self, c: &Coord) -> Cell {
+        if c.x < 0 || c.y < 0 || c.x >= self.width || c.y >= self.height { return Cell::WALL }
+        self.cells[c.y as usize * self.width as usize + c.x as usize].clone()
+    }
+
+    fn set(&mut self, c: &Coord, cell: Cell) {
+        self.cells[c.y as usize * self.width as usize + c.x as usize] = cell;
+    }
+
+    fn random_coord(&mut self) -> Coord {
+        Coord { x: self.rng.gen_range(0..self.width), y: self.rng.gen_range(0..self.height) }
+    }
+
+    fn random_dir(&mut self) -> PlayerDirection {
+        match self.rng.gen_range(0..4) {
+            0 => PlayerDirection::NORTH,
+            1 => PlayerDirection::EAST,
+            2 => PlayerDirection::SOUTH,
+            _ => PlayerDirection::WEST,
+        }
+    }
+
+    /// One of the blocks that `accept` takes, chosen at random, if there is any
+    fn sample(&mut self, accept: impl Fn(&Arena, &Coord) -> bool) -> Option<Coord> {
+        let blocks: Vec<Coord> = (0..self.width)
+            .flat_map(|x| (0..self.height).map(move |y| Coord { x, y }))
+            .filter(|c| accept(self, c))
+            .collect();
+        blocks.choose(&mut self.rng).cloned()
+    }
+
+    /// A floor block without items, if there is any left
+    fn free_coord(&mut self) -> Option<Coord> {
+        self.sample(|a, c| a.get(c) == Cell::FLOOR && !a.items.iter().any(|it| it.coord == *c))
+    }
+
+    /// A floor block without hazards around it nor players, or the closest thing to it
+    fn spawn_coord(&mut self) -> Coord {
+        let empty = |a: &Arena, c: &Coord| a.get(c) == Cell::FLOOR && a.player_at(c).is_none();
+        let hazard_around = |a: &Arena, c: &Coord| c.around().iter().any(|n| matches!(a.get(n), Cell::HOLE | Cell::TELEPORT(_)));
+        self.sample(|a, c| empty(a, c) && !hazard_around(a, c))
+            .or_else(|| self.sample(empty))
+            .unwrap_or_else(|| self.random_coord())
+    }
+}
+
+/// Plays a whole match in the local arena, with the AI of the config
+pub fn run_match(config: &Config, seed: u64, ticks: u32) -> MatchResult {
+    let mut arena = Arena::new(config, seed);
+    let mut ai = AI::headless(config, seed);
+
+    for tick in 0..ticks {
+        if tick as i32 % hunt::SCOREBOARD_TICKS == 0 { ai.opponents.update(&arena.scoreboard(), "") }
+        let action = ai.think(arena.bot_data());
+        arena.step(&action);
+        update::do_tick(&mut ai.field, config.normal_timer);
+    }
+    arena.result()
+}
@@ -0,0 +1,205 @@
+use crate::api::arena::Arena;
+use crate::api::config::Config;
+use crate::api::enums::PlayerDirection;
+use crate::api::map::{logic, query, Coord, Field, Position};
+use crate::api::map::distance::DistanceField;
+use crate::api::map::inference::{self, HazardBelief};
+use crate::api::map::node::{self, Node};
+use crate::api::map::path::Path;
+
+use ordered_float::OrderedFloat;
+use pathfinding::prelude::astar;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::{HashMap, HashSet};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+/// Times each block of the map is read, when timing the lookups
+const LOOKUP_ROUNDS: u32 = 200;
+
+pub struct BenchOptions {
+    pub paths: u32,
+    pub seed: u64,
+}
+
+impl BenchOptions {
+    pub fn default() -> BenchOptions {
+        BenchOptions { paths: 2000, seed: 0 }
+    }
+}
+
+/// Times the pathfinding and the reading of blocks, against the hashmaps the field used to keep the map in
+pub fn bench(config: &Config, options: &BenchOptions) {
+    let mut f = Field::new(config);
+    Arena::new(config, options.seed).reveal(&mut f);
+    let mut rng = StdRng::seed_from_u64(options.seed);
+
+    let floor: Vec<Coord> = f.grid.known()
+        .filter(|(_, p)| **p == Position::EMPTY)
+        .map(|(c, _)| c)
+        .collect();
+    if floor.is_empty() { println!("[BENCH] the map has no floor"); return }
+    let pairs: Vec<(Coord, Coord)> = (0..options.paths)
+        .map(|_| (floor[rng.gen_range(0..floor.len())].clone(), floor[rng.gen_range(0..floor.len())].clone()))
+        .collect();
+
+    // pathfinding
+    let start = Instant::now();
+    let mut found = 0;
+    let mut size = 0;
+    for (a, b) in pairs.iter() {
+        if let Some(p) = black_box(logic::a_star(&f, a, &PlayerDirection::NORTH, b)) {
+            found += 1;
+            size += p.size;
+        }
+    }
+    let elapsed = start.elapsed();
+    println!(
+        "[BENCH] a_star: {} paths ({} found, {:.1} steps on average) in {} ms, {:.1} us per path",
+        pairs.len(), found, size as f64 / found.max(1) as f64, elapsed.as_millis(), per(elapsed, pairs.len() as u32)
+    );
+
+    // the same paths, reading the blocks from hashmaps
+    let hashed = HashedField::new(&f);
+    let start = Instant::now();
+    let sizes: Vec<Option<usize>> = pairs.iter()
+        .map(|(a, b)| black_box(hashed.a_star(a, &PlayerDirection::NORTH, b)).map(|p| p.size))
+        .collect();
+    let hashed_elapsed = start.elapsed();
+    let differ = pairs.iter().zip(sizes.iter())
+        .filter(|((a, b), size)| logic::a_star(&f, a, &PlayerDirection::NORTH, b).map(|p| p.size) != **size)
+        .count();
+    println!(
+        "[BENCH] a_star over hashmaps: {} ms, {:.1} us per path | grid {:.1}x faster | {} paths differ",
+        hashed_elapsed.as_millis(), per(hashed_elapsed, pairs.len() as u32),
+        hashed_elapsed.as_secs_f64() / elapsed.as_secs_f64().max(1e-9), differ
+    );
+
+    // the same targets from one block: one A* for each, against one search for all of them
+    let origin = &pairs[0].0;
+    let start = Instant::now();
+    for (_, b) in pairs.iter() { black_box(logic::a_star(&f, origin, &PlayerDirection::NORTH, b)); }
+    let a_star = start.elapsed();
+    let start = Instant::now();
+    let distances = DistanceField::new(&f, origin, &PlayerDirection::NORTH);
+    let search = start.elapsed();
+    for (_, b) in pairs.iter() { black_box(distances.path(b)); }
+    let field = start.elapsed();
+    println!(
+        "[BENCH] distance field: {} targets from one block | a_star {} ms | one search {:.2} ms, {:.2} ms with the paths",
+        pairs.len(), a_star.as_millis(), 1000.0 * search.as_secs_f64(), 1000.0 * field.as_secs_f64()
+    );
+
+    // reading the blocks, with their neighbours, like the pathfinding does
+    let coords: Vec<Coord> = (0..f.grid.width())
+        .flat_map(|x| (0..f.grid.height()).map(move |y| Coord { x, y }))
+        .collect();
+    let reads = LOOKUP_ROUNDS * coords.len() as u32 * 5;
+
+    let start = Instant::now();
+    for _ in 0..LOOKUP_ROUNDS {
+        for c in coords.iter() {
+            black_box(query::get(&f, c));
+            for n in c.around() { black_box(query::get(&f, &n)); }
+        }
+    }
+    let grid = start.elapsed();
+
+    let hm: HashMap<Coord, Position> = f.grid.known().map(|(c, p)| (c, p.clone())).collect();
+    let start = Instant::now();
+    for _ in 0..LOOKUP_ROUNDS {
+        for c in coords.iter() {
+            black_box(hm.get(c).cloned().unwrap_or(Position::UNKNOWN));
+            for n in c.around() { black_box(hm.get(&n).cloned().unwrap_or(Position::UNKNOWN)); }
+        }
+    }
+    let hashed = start.elapsed();
+
+    println!(
+        "[BENCH] lookups: grid {:.1} ns | hashmap {:.1} ns | {:.1}x faster",
+        1000.0 * per(grid, reads), 1000.0 * per(hashed, reads), hashed.as_secs_f64() / grid.as_secs_f64().max(1e-9)
+    );
+}
+
+/// The blocks of the field as it used to keep them, in hashmaps keyed by the coordinates
+struct HashedField<'a> {
+    f: &'a Field,
+    positions: HashMap<Coord, Position>,
+    safe: HashSet<Coord>,
+    danger: HashMap<Coord, f64>,
+}
+
+impl HashedField<'_> {
+    fn new(f: &Field) -> HashedField<'_> {
+        let coords = (0..f.width()).flat_map(|x| (0..f.height()).map(move |y| Coord { x, y }));
+        HashedField {
+            f,
+            positions: f.grid.known().map(|(c, p)| (c, p.clone())).collect(),
+            safe: f.grid.safe().collect(),
+            danger: coords.filter(|c| query::is_unsafe(f, c)).map(|c| { let d = query::danger(f, &c); (c, d) }).collect(),
+        }
+    }
+
+    fn get(&self, c: &Coord) -> Position { self.positions.get(c).cloned().unwrap_or(Position::UNKNOWN) }
+
+    fn hazard(&self, c: &Coord) -> HazardBelief {
+        match self.get(c) {
+            Position::HOLE => HazardBelief { hole: 1.0, teleport: 0.0 },
+            Position::TELEPORT => HazardBelief { hole: 0.0, teleport: 1.0 },
+            Position::UNKNOWN | Position::SUSPECTED_HOLE | Position::SUSPECTED_TELEPORT => {
+                self.f.inference.belief(c).cloned().unwrap_or(
+                    HazardBelief { hole: inference::HAZARD_PRIOR, teleport: inference::HAZARD_PRIOR }
+                )
+            },
+            _ => HazardBelief::default()
+        }
+    }
+
+    fn neighbours(&self, n: &Node) -> Vec<(Node, OrderedFloat<f64>)> {
+        let p = &self.f.config.params;
+        let cost = |c: &Coord, is_back: bool| -> OrderedFloat<f64> {
+            let mut cost = if is_back { 1.0 + p.back_penalty } else { 1.0 };
+            if self.safe.contains(c) { cost *= p.safe_bonus }
+            cost += p.unsafe_penalty * self.danger.get(c).cloned().unwrap_or(0.0);
+            let h = self.hazard(c);
+            OrderedFloat(cost + h.hole * node::HOLE_COST + h.teleport * node::TELEPORT_RISK_COST)
+        };
+
+        let front = n.coord.next(&n.dir);
+        let back = n.coord.next(&n.dir.opposite());
+        let mut ret: Vec<(Node, OrderedFloat<f64>)> = vec![
+            (Node { coord: front.clone(), dir: n.dir.clone() }, cost(&front, false)),
+            (Node { coord: back.clone(), dir: n.dir.clone() }, cost(&back, true)),
+            (Node { coord: n.coord.clone(), dir: n.dir.left() }, OrderedFloat(1.0)),
+            (Node { coord: n.coord.clone(), dir: n.dir.right() }, OrderedFloat(1.0)),
+        ];
+        ret.retain(|(m, _)| match self.get(&m.coord) {
+            Position::WALL | Position::UNKNOWN | Position::HOLE | Position::TELEPORT => false,
+            Position::SUSPECTED_HOLE | Position::SUSPECTED_TELEPORT => {
+                let h = self.hazard(&m.coord);
+                h.hole < node::MAX_HOLE_PROBABILITY && h.teleport < node::MAX_TELEPORT_PROBABILITY
+            },
+            _ => true
+        });
+        if self.get(&front) == Position::TELEPORT {
+            if let Some(exit) = query::teleport_exit(self.f, &front) {
+                ret.push((Node { coord: exit, dir: n.dir.clone() }, OrderedFloat(node::TELEPORT_COST)));
+            }
+        }
+        ret
+    }
+
+    fn a_star(&self, origin: &Coord, dir: &PlayerDirection, dest: &Coord) -> Option<Path> {
+        let (nodes, _) = astar(
+            &Node { coord: origin.clone(), dir: dir.clone() },
+            |n: &Node| self.neighbours(n),
+            |n: &Node| n.distance_to_goal(self.f, dest),
+            |n: &Node| n.coord == *dest
+        )?;
+        Path::from_nodes(nodes)
+    }
+}
+
+/// Microseconds of each one of `n` runs
+fn per(d: Duration, n: u32) -> f64 { d.as_secs_f64() * 1e6 / n.max(1) as f64 }
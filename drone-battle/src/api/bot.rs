@@ -2,9 +2,12 @@ use crate::api::comms::{GameServer, SendCommand, RecvCommand, ServerCommand};
 use crate::api::structs::{ServerPlayer, ServerScoreboard, LastObservation};
 use crate::api::enums::{PlayerDirection, ServerState, Action};
 use crate::api::config::Config;
-use crate::api::map::update;
+use crate::api::map::{enemies, text, update, Coord};
+use crate::api::map::node::Node;
 use crate::api::ai::AI;
 use crate::api::graphics::Graphics;
+use crate::api::team::Team;
+use crate::api::hunt;
 
 use crossbeam_channel::{unbounded, Sender, Receiver, TryRecvError};
 use std::thread::{self, JoinHandle};
@@ -71,7 +74,8 @@ pub struct Bot {
     thread_handle: JoinHandle<()>,
 
     graphics: Option<Graphics>,
-
+    /// Channel to the other drones of the team, if any
+    team: Option<Team>,
 }
 
 impl Bot {
@@ -80,7 +84,7 @@ impl Bot {
     /// Also starts a `api::comms::GameServer` thread to communicate
     /// with the server.
     /// Use `.exit()`, that sends a command to close the thread, to end it.
-    pub fn new(config: Config, graphics: Option<Graphics>) -> Bot {
+    pub fn new(config: Config, graphics: Option<Graphics>, team: Option<Team>) -> Bot {
         // creating server listener
         let (tx_client, rx_server) = unbounded::<SendCommand>();
         let (tx_server, rx_client) = unbounded::<RecvCommand>();
@@ -114,7 +118,8 @@ impl Bot {
             last_time_damage: SystemTime::now(),
             exit_handler: create_exit_handler(),
             thread_handle: join_handle,
-            graphics
+            graphics,
+            team
         }
     }
 
@@ -122,6 +127,7 @@ impl Bot {
     /// Also consumes itself.
     pub fn exit(mut self) {
         if let Some(g) = self.graphics { g.close(); }
+        if let Some(t) = self.team { t.close(); }
         GameServer::do_this_command(
             &mut self.server.tx,
             SendCommand { command: ServerCommand::GOODBYE, attr: None}
@@ -159,6 +165,14 @@ impl Bot {
         println!("====================");
     }
 
+    /// Draws what the bot knew about the map when the game ended, as in `text::render`
+    fn print_map(&self) {
+        let bot = Node { coord: Coord { x: self.x, y: self.y }, dir: self.dir.clone() };
+        println!("==== MAP ====");
+        print!("{}", text::render(&self.ai.field, Some(&bot), self.ai.current_path.as_ref()));
+        println!("=============");
+    }
+
     /// Method to be used whenever the bot suffers some damage.
     ///
     /// Checks if the last damage happened too fast by the same bot.
@@ -237,7 +251,10 @@ impl Bot {
             else {
                 self.sleep(self.config.slow_timer);                 // sleep a bit
                 self.update_with_server(false);
-                if playing { self.say_all_chat("gg".to_string()) }      // say gg once
+                if playing {                                            // say gg once
+                    self.say_all_chat("gg".to_string());
+                    self.print_map();
+                }
                 playing = false;
                 self.ai.field.restart();
 
@@ -268,6 +285,11 @@ impl Bot {
 
     /// Helper method, containing the actions to be done after sending an action
     fn after_action(&mut self) {
+        if self.team.is_some() {
+            let botdata = BotData::from_bot(self);
+            let line = self.team.as_mut().and_then(|t| t.exchange(&mut self.ai, &botdata));
+            if let Some(line) = line { self.say_all_chat(line) }
+        }
         if self.graphics.is_some() {
             let botdata = BotData::from_bot(&self);
             self.graphics.as_mut().unwrap().update(
@@ -294,6 +316,13 @@ impl Bot {
             &mut self.server.tx,
             SendCommand { command: ServerCommand::GAMESTATUS, attr: None}
         ).ok();
+        // asking for the energy of the others, from time to time
+        if self.current_tick % hunt::SCOREBOARD_TICKS == 0 {
+            GameServer::do_this_command(
+                &mut self.server.tx,
+                SendCommand { command: ServerCommand::SCOREBOARD, attr: None}
+            ).ok();
+        }
         // println!("[BOT] sent gamestatus at {}", SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_millis() % 10000);
 
     }
@@ -301,6 +330,8 @@ impl Bot {
     /// Resets some variables, and send some initial commands to the server
     fn restart(&mut self) {
         self.current_tick = 0;
+        self.ai.opponents.clear();
+        if let Some(t) = self.team.as_mut() { t.restart(); }
         // asking for game status
         GameServer::do_this_command(
             &mut self.server.tx,
@@ -353,6 +384,7 @@ impl Bot {
                     // checking first if it can overwrite the hit or damage observations
                     let ishit = self.last_observation.is_hit;
                     let isdamage = self.last_observation.is_damage;
+                    let shooter = self.last_observation.shooter.clone();
                     let hasreaddamage = self.last_observation.has_read_damage;
                     let hasreadhit = self.last_observation.has_read_hit;
 
@@ -360,6 +392,7 @@ impl Bot {
 
                     if !hasreaddamage {
                         self.last_observation.is_damage = isdamage;
+                        self.last_observation.shooter = shooter;
                     };
                     if !hasreadhit {
                         self.last_observation.is_hit = ishit;
@@ -367,14 +400,24 @@ impl Bot {
                 },
                 RecvCommand::Status(ss) => {
                     checklist.user = true;
-                    self.x = ss.x as i16;
-                    self.y = ss.y as i16;
+                    self.x = ss.x;
+                    self.y = ss.y;
                     self.dir = ss.dir.clone();
                     self.state = ss.state.clone();
                     self.score = ss.score;
                     self.energy = ss.energy;
                 }
                 RecvCommand::Player(sp) => {
+                    if sp.x >= 0 && sp.y >= 0 {
+                        let c = Coord { x: sp.x, y: sp.y };
+                        self.ai.pending_change |= self.ai.field.fit(&c);
+                        // teammates are known by their messages, and are not enemies
+                        if self.team.as_ref().is_some_and(|t| t.is_teammate(&sp.name)) {
+                            enemies::absent(&mut self.ai.field, &c)
+                        } else if sp.name != self.config.name {
+                            enemies::seen(&mut self.ai.field, &c)
+                        }
+                    }
                     self.player_list.insert(sp.node, sp.clone());
                 }
                 RecvCommand::GameStatus(sgs) => {
@@ -384,6 +427,7 @@ impl Bot {
                     if sgs.status != ServerState::GAME { needs_checklist = false; }  // skip waiting, game is over
                 }
                 RecvCommand::Scoreboard(ss) => {
+                    self.ai.opponents.update(&ss.scoreboards, &self.config.name);
                     self.score_list = ss.clone();
                 }
                 RecvCommand::Notification(sn) => {
@@ -400,6 +444,8 @@ impl Bot {
                 }
                 RecvCommand::Hit(sh) => {
                     println!("[BOT LOG]: I hit [{}]", sh.target);
+                    let damage = self.ai.energy.damage_per_hit.round() as i32;
+                    self.ai.opponents.hit(&sh.target, damage);
                     self.last_observation.is_hit = true;
                     self.last_observation.has_read_hit = false;
                 }
@@ -408,6 +454,16 @@ impl Bot {
                     println!("[BOT DAMAGE]: [{}] damaged me", sd.shooter);
                     self.last_observation.is_damage = true;
                     self.last_observation.has_read_damage = false;
+                    // where the registry last saw the shooter
+                    self.last_observation.shooter = self.player_list.values()
+                        .find(|p| p.name == sd.shooter && p.x >= 0 && p.y >= 0)
+                        .map(|p| Coord { x: p.x, y: p.y });
+                }
+                RecvCommand::TeamChat(stc) => {
+                    if self.graphics.is_none() { println!("[BOT LOG]: team message from [{}]", stc.message.from); }
+                    if let Some(t) = self.team.as_mut() {
+                        self.ai.pending_change |= t.receive(&mut self.ai.field, stc.message);
+                    }
                 }
                 RecvCommand::Invalid(_) => {}
             }
@@ -457,6 +513,10 @@ pub struct BotData {
 }
 
 impl BotData {
+    pub fn new(x: i16, y: i16, dir: PlayerDirection, energy: i32, last_observation: LastObservation, score: i64) -> BotData {
+        BotData { x, y, dir, energy, last_observation, score }
+    }
+
     pub fn from_bot(bot: &Bot) -> BotData {
         BotData {
             x: bot.x.clone(),
@@ -7,9 +7,11 @@ use crate::api::{
         ServerGameStatus, ServerScoreboard,
         ServerNotification, ServerPlayerNew,
         ServerPlayerLeft, ServerChangeName,
-        ServerHit, ServerDamage
+        ServerHit, ServerDamage,
+        ServerTeamChat
     },
-    config::Config
+    config::Config,
+    team::chat
 };
 use std::{
     io::{Read, Write},
@@ -27,6 +29,8 @@ pub struct GameServer {
     server: Option<TcpStream>,
     drone_color: Color,
     drone_name: String,
+    /// key of the team messages in the chat
+    chat_secret: Option<String>,
 }
 
 impl GameServer {
@@ -36,7 +40,8 @@ impl GameServer {
             send_channel: sender,
             server: None,
             drone_color: config.default_color.clone(),
-            drone_name: config.name.clone()
+            drone_name: config.name.clone(),
+            chat_secret: config.chat_secret.clone()
         }
     }
 
@@ -100,10 +105,11 @@ impl GameServer {
             .expect("server clone failed");
 
         println!("[GAMESERVER] starting 'server_to_bot' thread");
+        let secret = self.chat_secret.clone();
         let handle = thread::Builder::new()
             .name("GAMESERVER server_to_bot".into())
             .spawn(move || {
-                GameServer::loop_server_to_bot(self.send_channel, server_clone);
+                GameServer::loop_server_to_bot(self.send_channel, server_clone, secret);
             })
             .unwrap();
 
@@ -142,7 +148,7 @@ impl GameServer {
         GameServer::close(server);
     }
 
-    fn loop_server_to_bot(sender: SenderChannel, mut server: TcpStream) {
+    fn loop_server_to_bot(sender: SenderChannel, mut server: TcpStream, secret: Option<String>) {
         loop {
             let mut recv_buffer = [0; 4096];
             match server.read(&mut recv_buffer) {
@@ -162,7 +168,7 @@ impl GameServer {
 
             let mut commands: Vec<RecvCommand> = Vec::new();
             for c in parse_buffer(recv_string) {
-                commands.push(parse_command(&c))
+                commands.push(parse_command(&c, secret.as_deref()))
             }
             // sending commands to client
             for cmd in commands {
@@ -242,6 +248,8 @@ pub enum RecvCommand {
     ChangeName(ServerChangeName),
     Hit(ServerHit),
     Damage(ServerDamage),
+    /// A message of a teammate, said in the chat
+    TeamChat(ServerTeamChat),
     Invalid(String)
 }
 
@@ -381,7 +389,7 @@ fn parse_observations(observations: String) -> LastObservation {
     last_observation
 }
 
-fn parse_command(cmd_str: &String) -> RecvCommand {
+fn parse_command(cmd_str: &String, secret: Option<&str>) -> RecvCommand {
     let to_be_trimmed: &[char] = &['\0', '\r'];
 
     let cmd: Vec<&str> = cmd_str.trim_matches(to_be_trimmed)
@@ -405,8 +413,8 @@ fn parse_command(cmd_str: &String) -> RecvCommand {
             if cmd.len() == 7 {
                 RecvCommand::Status(
                     ServerStatus {
-                        x: cmd[1].parse::<i8>().unwrap_or(-1),
-                        y: cmd[2].parse::<i8>().unwrap_or(-1),
+                        x: cmd[1].parse::<i16>().unwrap_or(-1),
+                        y: cmd[2].parse::<i16>().unwrap_or(-1),
                         dir: PlayerDirection::from_str(cmd[3]),
                         state: ServerState::from_str(cmd[4]),
                         score: cmd[5].parse::<i64>().unwrap_or(-123),
@@ -423,8 +431,8 @@ fn parse_command(cmd_str: &String) -> RecvCommand {
                     ServerPlayer {
                         node: cmd[1].parse::<i64>().unwrap_or(0),
                         name: cmd[2].to_string(),
-                        x: cmd[3].parse::<i8>().unwrap_or(-1),
-                        y: cmd[4].parse::<i8>().unwrap_or(-1),
+                        x: cmd[3].parse::<i16>().unwrap_or(-1),
+                        y: cmd[4].parse::<i16>().unwrap_or(-1),
                         dir: PlayerDirection::from_str(cmd[5]),
                         state: ServerState::from_str(cmd[6]),
                         color: Color::from_str(cmd[7])
@@ -469,6 +477,10 @@ fn parse_command(cmd_str: &String) -> RecvCommand {
             )
         },
         "notification" => {
+            // team messages are signed, so any other player saying something alike is just a notification
+            if let Some(m) = secret.and_then(|s| chat::decode(cmd[1], s)) {
+                return RecvCommand::TeamChat(ServerTeamChat { message: m })
+            }
             RecvCommand::Notification(
                 ServerNotification {
                     notification: cmd[1].to_string()
@@ -2,9 +2,14 @@ use serde::{Deserialize, Serialize};
 use serde_json as json;
 
 use crate::api::structs::Color;
+use crate::api::decision::DecisionTable;
+use crate::api::utility::UtilityConfig;
+use crate::api::policy::Policy;
+use crate::api::map::{MAP_WIDTH, MAP_HEIGHT, MAX_INFERRED_SIZE};
 
 use std::fs;
-use std::io::Error;
+use std::io::{Error, ErrorKind};
+use std::path::Path;
 use std::time::Duration;
 use rand::seq::SliceRandom;
 use std::collections::HashMap;
@@ -20,10 +25,132 @@ pub struct Config {
     pub default_color: Color,
     pub spawn_timer: Duration,
     pub graphics: bool,
+    pub decision_table: DecisionTable,
+    pub strategy: Strategy,
+    pub utility: UtilityConfig,
+    pub params: Parameters,
+    pub policy: Option<Policy>,
+    /// File to write the decision traces to, one JSON for each tick
+    pub trace: Option<String>,
+    /// Name of the team, to share the field with the other drones of the team
+    pub team: Option<String>,
+    /// Key of the team messages in the game chat. Without it, the chat is not used
+    pub chat_secret: Option<String>,
+    /// Size of the map, in blocks
+    pub map_width: i16,
+    pub map_height: i16,
+    /// Grows the map when someone is seen out of it, for servers with a map of unknown size
+    pub infer_map_size: bool,
+}
+
+/// Numbers that change how the bot plays. They can be searched with the `tune` subcommand
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct Parameters {
+    /// Recharges when standing on a powerup with this energy or less
+    pub recharge_on_powerup_energy: f64,
+    /// Goes after a powerup with this energy or less
+    pub recharge_energy: f64,
+    /// Only attacks with more energy than this
+    pub min_attack_energy: f64,
+    /// Runs from enemies around with less energy than this
+    pub run_energy: f64,
+    /// Ticks to keep running after starting to run
+    pub ticks_running: f64,
+    /// Maximum shots in a row
+    pub max_ticks_attacking: f64,
+    /// Extra cost of walking backwards in a path
+    pub back_penalty: f64,
+    /// Cost multiplier of walking into a safe block not explored yet
+    pub safe_bonus: f64,
+    /// Extra cost of walking into a block where the bot took damage
+    pub unsafe_penalty: f64,
+    /// Hunts opponents with this energy or less
+    pub hunt_energy: f64,
+    /// Only hunts with more energy than this
+    pub min_hunt_energy: f64,
+    /// Maximum ticks hunting the same enemy
+    pub max_ticks_hunting: f64,
+}
+
+impl Default for Parameters {
+    fn default() -> Parameters {
+        Parameters {
+            recharge_on_powerup_energy: 70.0,
+            recharge_energy: 80.0,
+            min_attack_energy: 30.0,
+            run_energy: 30.0,
+            ticks_running: 5.0,
+            max_ticks_attacking: 10.0,
+            back_penalty: 1.5,
+            safe_bonus: 0.8,
+            unsafe_penalty: 10.0,
+            hunt_energy: 30.0,
+            min_hunt_energy: 60.0,
+            max_ticks_hunting: 30.0,
+        }
+    }
+}
+
+impl Parameters {
+    /// Gets a parameter by its name, as used in the decision table
+    pub fn get(&self, name: &str) -> Option<f64> {
+        match name {
+            "recharge_on_powerup_energy" => Some(self.recharge_on_powerup_energy),
+            "recharge_energy" => Some(self.recharge_energy),
+            "min_attack_energy" => Some(self.min_attack_energy),
+            "run_energy" => Some(self.run_energy),
+            "ticks_running" => Some(self.ticks_running),
+            "max_ticks_attacking" => Some(self.max_ticks_attacking),
+            "back_penalty" => Some(self.back_penalty),
+            "safe_bonus" => Some(self.safe_bonus),
+            "unsafe_penalty" => Some(self.unsafe_penalty),
+            "hunt_energy" => Some(self.hunt_energy),
+            "min_hunt_energy" => Some(self.min_hunt_energy),
+            "max_ticks_hunting" => Some(self.max_ticks_hunting),
+            _ => None
+        }
+    }
+
+    /// Sets a parameter by its name. Returns false if there is no such parameter
+    pub fn set(&mut self, name: &str, value: f64) -> bool {
+        let p = match name {
+            "recharge_on_powerup_energy" => &mut self.recharge_on_powerup_energy,
+            "recharge_energy" => &mut self.recharge_energy,
+            "min_attack_energy" => &mut self.min_attack_energy,
+            "run_energy" => &mut self.run_energy,
+            "ticks_running" => &mut self.ticks_running,
+            "max_ticks_attacking" => &mut self.max_ticks_attacking,
+            "back_penalty" => &mut self.back_penalty,
+            "safe_bonus" => &mut self.safe_bonus,
+            "unsafe_penalty" => &mut self.unsafe_penalty,
+            "hunt_energy" => &mut self.hunt_energy,
+            "min_hunt_energy" => &mut self.min_hunt_energy,
+            "max_ticks_hunting" => &mut self.max_ticks_hunting,
+            _ => return false
+        };
+        *p = value;
+        true
+    }
+}
+
+/// How the AI chooses its state at each tick
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Strategy {
+    /// First matching rule of the decision table
+    #[default]
+    DecisionTable,
+    /// Behaviour with the highest utility score
+    Utility,
+    /// Action chosen by a learned policy, skipping the states
+    Policy,
+    /// Action chosen by a tree search over the field, skipping the states
+    Mcts,
 }
 
 impl Config {
-    pub fn from_config_json(c: ConfigJSON) -> Config {
+    pub fn from_config_json(c: ConfigJSON, decision_table: DecisionTable, utility: UtilityConfig, policy: Option<Policy>) -> Config {
         let default_color = Color { r: 0, g: 0, b: 0, a: 0};
         Config {
             name: c.name,
@@ -38,7 +165,18 @@ impl Config {
                 a: c.default_color.get("a").unwrap_or(&default_color.a).clone(),
             },
             spawn_timer: Duration::from_millis(c.spawn_timer),
-            graphics: c.graphics
+            graphics: c.graphics,
+            decision_table,
+            strategy: c.strategy,
+            utility,
+            params: c.parameters,
+            policy,
+            trace: c.trace,
+            team: c.team,
+            chat_secret: c.chat_secret,
+            map_width: c.map_width.unwrap_or(MAP_WIDTH).clamp(1, MAX_INFERRED_SIZE),
+            map_height: c.map_height.unwrap_or(MAP_HEIGHT).clamp(1, MAX_INFERRED_SIZE),
+            infer_map_size: c.infer_map_size,
         }
     }
 }
@@ -53,7 +191,37 @@ pub struct ConfigJSON {
     pub min_timer: u64,
     pub default_color: HashMap<String, u8>,
     pub spawn_timer: u64,
-    pub graphics: bool
+    pub graphics: bool,
+    /// Path to the decision table, relative to the config file
+    #[serde(default)]
+    pub decision_table: Option<String>,
+    #[serde(default)]
+    pub strategy: Strategy,
+    /// Path to the utility curves, relative to the config file
+    #[serde(default)]
+    pub utility: Option<String>,
+    #[serde(default)]
+    pub parameters: Parameters,
+    /// Path to the learned policy, relative to the config file. Needed by the "policy" strategy
+    #[serde(default)]
+    pub policy: Option<String>,
+    /// Path to write the decision traces to, relative to the config file
+    #[serde(default)]
+    pub trace: Option<String>,
+    /// Drones with the same team share what they know over the local MQTT broker
+    #[serde(default)]
+    pub team: Option<String>,
+    /// Drones with the same secret also share their positions, golds and claims through the game chat
+    #[serde(default)]
+    pub chat_secret: Option<String>,
+    /// Size of the map of the server, 59x34 if not told
+    #[serde(default)]
+    pub map_width: Option<i16>,
+    #[serde(default)]
+    pub map_height: Option<i16>,
+    /// Takes the size above as a first guess, and grows it to fit the positions seen
+    #[serde(default)]
+    pub infer_map_size: bool,
 }
 
 
@@ -67,18 +235,49 @@ impl Config {
             min_timer: Duration::from_millis(100),
             default_color: random_color(),
             spawn_timer: Duration::from_millis(15000),
-            graphics: true
+            graphics: true,
+            decision_table: DecisionTable::default(),
+            strategy: Strategy::DecisionTable,
+            utility: UtilityConfig::default(),
+            params: Parameters::default(),
+            policy: None,
+            trace: None,
+            team: None,
+            chat_secret: None,
+            map_width: MAP_WIDTH,
+            map_height: MAP_HEIGHT,
+            infer_map_size: false,
         }
     }
 
     pub fn load(filename: String) -> Result<Config, Error> {
         // opening file
-        let content = fs::read_to_string(filename)?;
+        let content = fs::read_to_string(&filename)?;
 
         // autoparsing
         let c: ConfigJSON = json::from_str(content.as_str())?;
 
-        Ok(Config::from_config_json(c))
+        // loading the decision table, utility curves and policy, if any
+        let dir = Path::new(&filename).parent().unwrap_or(Path::new(""));
+        let decision_table = match &c.decision_table {
+            Some(dt) => DecisionTable::load(dir.join(dt).to_string_lossy().as_ref())?,
+            None => DecisionTable::default()
+        };
+        let utility = match &c.utility {
+            Some(u) => UtilityConfig::load(dir.join(u).to_string_lossy().as_ref())?,
+            None => UtilityConfig::default()
+        };
+
+        let policy = match &c.policy {
+            Some(p) => Some(Policy::load(dir.join(p).to_string_lossy().as_ref())?),
+            None => None
+        };
+        if c.strategy == Strategy::Policy && policy.is_none() {
+            return Err(Error::new(ErrorKind::InvalidInput, "the \"policy\" strategy needs a \"policy\" file"))
+        }
+
+        let trace = c.trace.as_ref().map(|t| dir.join(t).to_string_lossy().to_string());
+        Ok(Config { trace, ..Config::from_config_json(c, decision_table, utility, policy) })
     }
 }
 
@@ -0,0 +1,339 @@
+use serde::Deserialize;
+use serde_json as json;
+
+use crate::api::ai::BotState;
+use crate::api::config::Parameters;
+
+use std::collections::HashSet;
+use std::fs;
+use std::io::{Error, ErrorKind};
+
+/// Used when none is given in the config: the original state machine, with search, hunt and timed recharge rules
+const DEFAULT_TABLE: &str = include_str!("../../decision_table.json");
+
+/// Rules that choose the state of the bot at each tick, where the first one that matches wins
+#[derive(Clone, Debug)]
+pub struct DecisionTable {
+    pub rules: Vec<Rule>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Rule {
+    pub name: String,
+    pub when: Condition,
+    pub state: BotState,
+    #[serde(default)]
+    pub then: Vec<RuleAction>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Condition {
+    Always,
+    All(Vec<Condition>),
+    Any(Vec<Condition>),
+    Not(Box<Condition>),
+    Percept(Percept),
+    Energy(Comparison),
+    Counter(CounterComparison),
+    Query(FieldQuery),
+}
+
+/// Value of a condition in a tick, with the values of the conditions inside it that were checked
+pub struct Checked {
+    pub value: bool,
+    /// In order, up to the one that decided the value
+    pub parts: Vec<Checked>,
+}
+
+/// Observations from the last tick
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Percept {
+    EnemyFront,
+    Blocked,
+    Steps,
+    Breeze,
+    Flash,
+    Treasure,
+    Powerup,
+    Damage,
+    Hit,
+}
+
+/// Questions about the field, answered by the AI
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldQuery {
+    /// There is a wall between the bot and the enemy in front of it
+    WallFront,
+    HasGold,
+    HasPowerup,
+    /// Leaving now, the bot arrives at a powerup about when it spawns
+    RechargeReady,
+    /// At the expected energy cost of each tick, the bot must go to a powerup now, or the energy may be over first
+    RechargeDue,
+    /// There is a route to collect some gold
+    GoldRoute,
+    /// Some opponent has `hunt_energy` or less, and an enemy is probably nearby
+    HuntTarget,
+}
+
+/// Counters kept by the AI between ticks
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "snake_case")]
+#[allow(clippy::enum_variant_names)]
+pub enum Counter {
+    TicksRunning,
+    TicksAttacking,
+    TicksHunting,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub enum Operator {
+    #[serde(rename = "<")] LT,
+    #[serde(rename = "<=")] LE,
+    #[serde(rename = ">")] GT,
+    #[serde(rename = ">=")] GE,
+    #[serde(rename = "==")] EQ,
+    #[serde(rename = "!=")] NE,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Comparison {
+    pub op: Operator,
+    pub value: Value,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct CounterComparison {
+    pub name: Counter,
+    pub op: Operator,
+    pub value: Value,
+}
+
+/// A number, or the name of a tunable parameter, as in `{ "param": "run_energy" }`
+#[derive(Deserialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum Value {
+    Number(f64),
+    Param { param: String },
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleAction {
+    Set(CounterChange),
+    Add(CounterChange),
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct CounterChange {
+    pub counter: Counter,
+    pub value: Value,
+}
+
+impl Comparison {
+    pub fn check(&self, x: i32, params: &Parameters) -> bool { self.op.check(x as f64, self.value.get(params)) }
+}
+
+impl CounterComparison {
+    pub fn check(&self, x: i32, params: &Parameters) -> bool { self.op.check(x as f64, self.value.get(params)) }
+}
+
+impl CounterChange {
+    pub fn value(&self, params: &Parameters) -> i32 { self.value.get(params).round() as i32 }
+}
+
+impl Value {
+    pub fn get(&self, params: &Parameters) -> f64 {
+        match self {
+            Value::Number(n) => *n,
+            Value::Param { param } => params.get(param).unwrap_or(0.0),
+        }
+    }
+
+    /// Returns what is wrong with the value, if anything
+    fn validate(&self) -> Option<String> {
+        match self {
+            Value::Param { param } if Parameters::default().get(param).is_none() =>
+                Some(format!("unknown parameter '{}'", param)),
+            _ => None
+        }
+    }
+}
+
+impl Operator {
+    pub fn symbol(&self) -> &str {
+        match self {
+            Operator::LT => "<",
+            Operator::LE => "<=",
+            Operator::GT => ">",
+            Operator::GE => ">=",
+            Operator::EQ => "==",
+            Operator::NE => "!=",
+        }
+    }
+
+    pub fn check(&self, x: f64, y: f64) -> bool {
+        match self {
+            Operator::LT => x < y,
+            Operator::LE => x <= y,
+            Operator::GT => x > y,
+            Operator::GE => x >= y,
+            Operator::EQ => x == y,
+            Operator::NE => x != y,
+        }
+    }
+}
+
+impl DecisionTable {
+    pub fn default() -> DecisionTable {
+        DecisionTable::from_str(DEFAULT_TABLE).expect("invalid default decision table")
+    }
+
+    pub fn load(filename: &str) -> Result<DecisionTable, Error> {
+        let content = fs::read_to_string(filename)?;
+        DecisionTable::from_str(content.as_str())
+            .map_err(|e| Error::new(ErrorKind::InvalidData, format!("{}: {}", filename, e)))
+    }
+
+    /// Errors point to the offending rule, by its position and name
+    pub fn from_str(content: &str) -> Result<DecisionTable, String> {
+        let value: json::Value = json::from_str(content).map_err(|e| e.to_string())?;
+        let raw_rules = value.get("rules")
+            .and_then(|r| r.as_array())
+            .ok_or("missing \"rules\" list")?;
+
+        let mut rules: Vec<Rule> = Vec::new();
+        for (i, raw) in raw_rules.iter().enumerate() {
+            let rule: Rule = Rule::deserialize(raw).map_err(|e| {
+                let name = raw.get("name").and_then(|n| n.as_str()).unwrap_or("?");
+                format!("rule #{} '{}': {}", i, name, e)
+            })?;
+            rules.push(rule);
+        }
+
+        let table = DecisionTable { rules };
+        table.validate()?;
+        Ok(table)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let mut names: HashSet<&str> = HashSet::new();
+
+        for (i, rule) in self.rules.iter().enumerate() {
+            let error = |msg: &str| format!("rule #{} '{}': {}", i, rule.name, msg);
+
+            if rule.name.is_empty() { return Err(error("empty name")) }
+            if !names.insert(rule.name.as_str()) { return Err(error("duplicated name")) }
+            if rule.state == BotState::NONE { return Err(error("NONE is not a valid state")) }
+            if let Some(msg) = rule.when.validate() { return Err(error(&msg)) }
+            let change = |a: &RuleAction| match a { RuleAction::Set(c) | RuleAction::Add(c) => c.value.validate() };
+            if let Some(msg) = rule.then.iter().find_map(change) { return Err(error(&msg)) }
+
+            let is_last = i == self.rules.len() - 1;
+            let always = matches!(rule.when, Condition::Always);
+            if always && !is_last { return Err(error("always matches, so the rules after it are never used")) }
+            if !always && is_last { return Err(error("the last rule must be \"always\"")) }
+        }
+
+        if self.rules.is_empty() { return Err("no rules".to_string()) }
+        Ok(())
+    }
+}
+
+impl Condition {
+    /// Returns what is wrong with the condition, if anything
+    fn validate(&self) -> Option<String> {
+        match self {
+            Condition::All(v) | Condition::Any(v) => {
+                if v.is_empty() { return Some("empty \"all\" or \"any\"".to_string()) }
+                v.iter().find_map(|c| c.validate())
+            },
+            Condition::Not(c) => c.validate(),
+            Condition::Energy(c) => c.value.validate(),
+            Condition::Counter(c) => c.value.validate(),
+            _ => None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(content: &str) -> String {
+        DecisionTable::from_str(content).unwrap_err()
+    }
+
+    const EXPLORE: &str = r#"{ "name": "explore", "when": "always", "state": "EXPLORE" }"#;
+
+    fn table(rules: &[&str]) -> String {
+        format!(r#"{{ "rules": [ {} ] }}"#, rules.join(", "))
+    }
+
+    #[test]
+    fn default_table_is_valid() {
+        let table = DecisionTable::default();
+        assert_eq!(table.rules.last().map(|r| r.name.as_str()), Some("explore"));
+    }
+
+    #[test]
+    fn missing_or_empty_rules() {
+        assert_eq!(error("{}"), "missing \"rules\" list");
+        assert_eq!(error(r#"{ "rules": [] }"#), "no rules");
+        assert!(DecisionTable::from_str("{ \"rules\": ").is_err());
+    }
+
+    #[test]
+    fn rule_names() {
+        let unnamed = r#"{ "name": "", "when": { "percept": "treasure" }, "state": "COLLECT" }"#;
+        assert_eq!(error(&table(&[unnamed, EXPLORE])), "rule #0 '': empty name");
+        let explore = r#"{ "name": "explore", "when": { "percept": "flash" }, "state": "EXPLORE" }"#;
+        assert_eq!(error(&table(&[explore, EXPLORE])), "rule #1 'explore': duplicated name");
+    }
+
+    #[test]
+    fn none_state() {
+        let none = r#"{ "name": "idle", "when": "always", "state": "NONE" }"#;
+        assert_eq!(error(&table(&[none])), "rule #0 'idle': NONE is not a valid state");
+    }
+
+    #[test]
+    fn always_must_be_last() {
+        let collect = r#"{ "name": "collect", "when": { "percept": "treasure" }, "state": "COLLECT" }"#;
+        assert_eq!(error(&table(&[EXPLORE, collect])),
+                   "rule #0 'explore': always matches, so the rules after it are never used");
+        assert_eq!(error(&table(&[collect])), "rule #0 'collect': the last rule must be \"always\"");
+    }
+
+    #[test]
+    fn unknown_parameters() {
+        let when = r#"{ "name": "run", "when": { "energy": { "op": "<", "value": { "param": "nope" } } }, "state": "RUN" }"#;
+        assert_eq!(error(&table(&[when, EXPLORE])), "rule #0 'run': unknown parameter 'nope'");
+
+        let then = r#"{ "name": "run", "when": { "percept": "damage" }, "state": "RUN",
+                        "then": [ { "set": { "counter": "ticks_running", "value": { "param": "nope" } } } ] }"#;
+        assert_eq!(error(&table(&[then, EXPLORE])), "rule #0 'run': unknown parameter 'nope'");
+    }
+
+    #[test]
+    fn empty_all_or_any() {
+        let rule = r#"{ "name": "run", "when": { "not": { "any": [] } }, "state": "RUN" }"#;
+        assert_eq!(error(&table(&[rule, EXPLORE])), "rule #0 'run': empty \"all\" or \"any\"");
+    }
+
+    #[test]
+    fn bad_rules_are_named() {
+        let rule = r#"{ "name": "fly", "when": { "percept": "wings" }, "state": "EXPLORE" }"#;
+        assert!(error(&table(&[EXPLORE, rule])).starts_with("rule #1 'fly': "));
+
+        let extra = r#"{ "name": "fly", "when": "always", "state": "EXPLORE", "speed": 2 }"#;
+        assert!(error(&table(&[extra])).starts_with("rule #0 'fly': "));
+    }
+}
@@ -0,0 +1,134 @@
+use crate::api::enums::{Action, PlayerDirection};
+use crate::api::map::{Coord, Field};
+use crate::api::map::distance;
+use crate::api::map::path::Path;
+use crate::api::structs::LastObservation;
+
+use std::cmp::Reverse;
+
+/// Weight of the newest observation in the averages
+const LEARNING_RATE: f64 = 0.1;
+/// Damage of a shot, until one is observed
+const DEFAULT_DAMAGE: f64 = 10.0;
+/// Energy the bot wants to have left when arriving at a powerup
+const MIN_ARRIVAL_ENERGY: f64 = 10.0;
+/// Ticks the bot may arrive early at a powerup
+pub const LOITER_TICKS: u32 = 3;
+/// Extra ticks to start a recharge trip earlier than needed
+const RECHARGE_MARGIN: u32 = 20;
+
+/// Learns the cost of each action and the damage of a hit from the energy told at each tick
+pub struct EnergyModel {
+    // average energy spent by each action, in the order of `action_index`
+    costs: [f64; 7],
+    // average energy lost by each hit
+    pub damage_per_hit: f64,
+    // average hits taken per tick
+    pub hit_rate: f64,
+    last_energy: Option<i32>,
+}
+
+/// A trip to a powerup, timed to arrive as it spawns
+pub struct RechargePlan {
+    pub target: Coord,
+    pub path: Path,
+    // ticks to wait before leaving, so the bot arrives when the powerup is ready
+    pub wait: u32,
+    // ticks from now until the powerup is collected
+    pub ticks: u32,
+    // false if the bot is expected to run out of energy on the way
+    pub in_time: bool,
+}
+
+impl EnergyModel {
+    pub fn new() -> EnergyModel {
+        EnergyModel { costs: [0.0; 7], damage_per_hit: DEFAULT_DAMAGE, hit_rate: 0.0, last_energy: None }
+    }
+
+    pub fn observe(&mut self, last_action: &Action, energy: i32, o: &LastObservation) {
+        let last = self.last_energy.replace(energy);
+        let last = match last {
+            Some(l) if l > 0 && energy > 0 => l,
+            _ => return,        // dead or respawned, nothing to learn
+        };
+        let delta = (energy - last) as f64;
+        if delta > 0.0 { return }       // powerup collected
+
+        let i = action_index(last_action);
+        self.hit_rate += LEARNING_RATE * (if o.is_damage { 1.0 } else { 0.0 } - self.hit_rate);
+        // with damage, what is left after the cost of the action is the damage of one hit
+        if o.is_damage {
+            let damage = -delta - self.costs[i];
+            if damage > 0.0 { self.damage_per_hit += LEARNING_RATE * (damage - self.damage_per_hit) }
+        } else {
+            self.costs[i] += LEARNING_RATE * (-delta - self.costs[i]);
+        }
+    }
+
+    pub fn cost(&self, action: &Action) -> f64 { self.costs[action_index(action)] }
+
+    /// Expected energy spent in one tick while walking, hits included
+    pub fn cost_per_tick(&self) -> f64 {
+        self.cost(&Action::FRONT) + self.hit_rate * self.damage_per_hit
+    }
+
+    pub fn energy_after(&self, energy: i32, ticks: u32) -> f64 {
+        energy as f64 - self.cost_per_tick() * ticks as f64
+    }
+
+    /// None if the energy is not going down
+    pub fn ticks_to_empty(&self, energy: i32) -> Option<u32> {
+        let cost = self.cost_per_tick();
+        if cost <= f64::EPSILON { None } else { Some((energy as f64 / cost) as u32) }
+    }
+
+    /// The energy may be over before the bot gets to the powerup, if it does not leave now
+    pub fn recharge_due(&self, energy: i32, plan: &RechargePlan) -> bool {
+        self.ticks_to_empty(energy).is_some_and(|t| t <= plan.ticks + RECHARGE_MARGIN)
+    }
+}
+
+impl RechargePlan {
+    /// Leaving now, the bot arrives about when the powerup spawns
+    pub fn is_ready(&self) -> bool { self.in_time && self.wait <= LOITER_TICKS }
+}
+
+impl Default for EnergyModel {
+    fn default() -> Self { EnergyModel::new() }
+}
+
+fn action_index(a: &Action) -> usize {
+    match a {
+        Action::FRONT => 0,
+        Action::BACK => 1,
+        Action::LEFT => 2,
+        Action::RIGHT => 3,
+        Action::GET => 4,
+        Action::SHOOT => 5,
+        Action::NOTHING => 6,
+    }
+}
+
+/// Trip to the powerup that recharges the bot the soonest, waiting to leave so it arrives as it spawns
+pub fn plan(f: &Field, c: &Coord, dir: &PlayerDirection, energy: i32, model: &EnergyModel) -> Option<RechargePlan> {
+    let tick = f.config.normal_timer.as_millis().max(1);
+    let mut best: Option<RechargePlan> = None;
+    let distances = distance::from(f, c, dir);
+
+    for (p, timer) in f.powerup_positions.iter() {
+        let path = match distances.path(p) {
+            Some(path) => path,
+            None => continue,
+        };
+        let travel = path.size as u32;
+        let in_time = model.energy_after(energy, travel) >= MIN_ARRIVAL_ENERGY;
+
+        let ready_in = (f.config.spawn_timer.saturating_sub(*timer).as_millis() / tick) as u32;
+        let wait = ready_in.saturating_sub(travel);
+        let ticks = travel + wait;
+        if best.as_ref().is_none_or(|b| (in_time, Reverse(ticks)) > (b.in_time, Reverse(b.ticks))) {
+            best = Some(RechargePlan { target: p.clone(), path, wait, ticks, in_time });
+        }
+    }
+    best
+}
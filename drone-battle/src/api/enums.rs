@@ -35,6 +35,10 @@ pub enum PlayerDirection {
 }
 
 impl PlayerDirection {
+    pub fn all() -> [PlayerDirection; 4] {
+        [PlayerDirection::NORTH, PlayerDirection::EAST, PlayerDirection::SOUTH, PlayerDirection::WEST]
+    }
+
     pub fn from_str(dir: &str) -> PlayerDirection {
         match dir {
             "north" => PlayerDirection::NORTH,
@@ -0,0 +1,186 @@
+use serde::{Deserialize, Serialize};
+use serde_json as json;
+
+use crate::api::ai::AI;
+use crate::api::arena::Arena;
+use crate::api::bot::BotData;
+use crate::api::config::Config;
+use crate::api::enums::{Action, PlayerDirection};
+use crate::api::map::{Coord, Field, Position};
+use crate::api::map::{query, update};
+use crate::api::structs::LastObservation;
+
+use std::io::{self, BufRead, Write};
+use std::time::Duration;
+
+/// Side of the square crop of the field around the bot. Odd, so the bot is in the middle
+pub const CROP_SIZE: usize = 11;
+/// Values for each block of the crop: wall, unknown, free, hole, teleport, gold, powerup
+pub const CROP_CHANNELS: usize = 7;
+/// Percepts (9 flags and the distance of the enemy in front), pose (x, y and 4 for the direction) and energy
+pub const STATE_SIZE: usize = 10 + 6 + 1;
+pub const OBSERVATION_SIZE: usize = STATE_SIZE + CROP_SIZE * CROP_SIZE * CROP_CHANNELS;
+
+/// Actions, in the order used by policies
+pub const ACTIONS: [Action; 7] = [
+    Action::FRONT, Action::BACK, Action::LEFT, Action::RIGHT, Action::GET, Action::SHOOT, Action::NOTHING
+];
+
+/// Extra information of a step, not meant for the policy
+#[derive(Serialize, Clone, Debug)]
+pub struct Info {
+    pub tick: u32,
+    pub score: i64,
+    pub golds: u32,
+    pub deaths: u32,
+}
+
+/// The game as a reinforcement learning environment, where the observation only has what the bot could know
+pub struct Env {
+    config: Config,
+    arena: Arena,
+    ai: AI,
+    max_ticks: u32,
+    tick: u32,
+    score: i64,
+}
+
+impl Env {
+    pub fn new(config: &Config, max_ticks: u32) -> Env {
+        Env {
+            config: config.clone(),
+            arena: Arena::new(config, 0),
+            ai: AI::headless(config, 0),
+            max_ticks,
+            tick: 0,
+            score: 0,
+        }
+    }
+
+    /// Starts a new match. The same seed always gives the same match
+    pub fn reset(&mut self, seed: u64) -> Vec<f32> {
+        self.arena = Arena::new(&self.config, seed);
+        self.ai = AI::headless(&self.config, seed);
+        self.tick = 0;
+        self.score = 0;
+
+        let bot = self.arena.bot_data();
+        self.ai.perceive(&bot, &Action::NOTHING);
+        encode(&self.ai.field, &bot)
+    }
+
+    /// Runs one tick. The reward is the change of score
+    pub fn step(&mut self, action: &Action) -> (Vec<f32>, f64, bool, Info) {
+        self.arena.step(action);
+        update::do_tick(&mut self.ai.field, self.config.normal_timer);
+        self.tick += 1;
+
+        let bot = self.arena.bot_data();
+        self.ai.perceive(&bot, action);
+
+        let result = self.arena.result();
+        let reward = (result.score - self.score) as f64;
+        self.score = result.score;
+
+        let info = Info { tick: self.tick, score: result.score, golds: result.golds, deaths: result.deaths };
+        (encode(&self.ai.field, &bot), reward, self.tick >= self.max_ticks, info)
+    }
+}
+
+/// Requests of `serve`, one JSON for each line
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+enum Request {
+    // starts a match with the seed
+    Reset(u64),
+    // plays the action, by its index in `ACTIONS`
+    Step(usize),
+}
+
+#[derive(Serialize)]
+struct Response {
+    observation: Vec<f32>,
+    reward: f64,
+    done: bool,
+    info: Option<Info>,
+}
+
+/// Serves the environment over stdin and stdout, one JSON for each line
+pub fn serve(config: &Config, max_ticks: u32) -> io::Result<()> {
+    let mut env = Env::new(config, max_ticks);
+    let mut out = io::stdout().lock();
+
+    for line in io::stdin().lock().lines() {
+        let line = line?;
+        if line.trim().is_empty() { continue }
+
+        let response = match json::from_str::<Request>(&line) {
+            Ok(Request::Reset(seed)) => Ok(Response { observation: env.reset(seed), reward: 0.0, done: false, info: None }),
+            Ok(Request::Step(a)) if a < ACTIONS.len() => {
+                let (observation, reward, done, info) = env.step(&ACTIONS[a]);
+                Ok(Response { observation, reward, done, info: Some(info) })
+            },
+            Ok(Request::Step(a)) => Err(format!("invalid action {}", a)),
+            Err(e) => Err(e.to_string()),
+        };
+        let text = match response {
+            Ok(r) => json::to_string(&r)?,
+            Err(e) => json::json!({ "error": e }).to_string(),
+        };
+        writeln!(out, "{}", text)?;
+        out.flush()?;
+    }
+    Ok(())
+}
+
+/// What the bot knows as `OBSERVATION_SIZE` values between 0 and 1, cropped around the bot looking up
+pub fn encode(f: &Field, bot: &BotData) -> Vec<f32> {
+    let o: LastObservation = bot.get_last_observation();
+    let c: Coord = Coord { x: bot.get_x(), y: bot.get_y() };
+    let dir: PlayerDirection = bot.get_dir();
+    let flag = |b: bool| if b { 1.0 } else { 0.0 };
+
+    let mut v: Vec<f32> = Vec::with_capacity(OBSERVATION_SIZE);
+    v.extend([
+        flag(o.is_enemy_front), flag(o.is_blocked), flag(o.is_steps), flag(o.is_breeze), flag(o.is_flash),
+        flag(o.is_treasure), flag(o.is_powerup), flag(o.is_damage), flag(o.is_hit),
+        if o.distance_enemy_front > 0 { 1.0 / o.distance_enemy_front as f32 } else { 0.0 },
+    ]);
+    v.extend([c.x as f32 / f.width() as f32, c.y as f32 / f.height() as f32]);
+    v.extend([PlayerDirection::NORTH, PlayerDirection::EAST, PlayerDirection::SOUTH, PlayerDirection::WEST]
+        .iter().map(|d| flag(*d == dir)));
+    v.push(bot.get_energy() as f32 / 100.0);
+
+    // unit vectors of front and right, in map coordinates
+    let front = c.next(&dir);
+    let right = c.next(&dir.right());
+    let (fx, fy) = (front.x - c.x, front.y - c.y);
+    let (rx, ry) = (right.x - c.x, right.y - c.y);
+    let half = (CROP_SIZE / 2) as i16;
+
+    for row in 0..CROP_SIZE as i16 {
+        for col in 0..CROP_SIZE as i16 {
+            let ahead = half - row;
+            let side = col - half;
+            let b = Coord { x: c.x + fx * ahead + rx * side, y: c.y + fy * ahead + ry * side };
+            v.extend(encode_block(f, &b));
+        }
+    }
+    v
+}
+
+fn encode_block(f: &Field, c: &Coord) -> [f32; CROP_CHANNELS] {
+    let p = query::get(f, c);
+    let h = query::hazard(f, c);
+    let ready = |t: Option<&Duration>| t.map_or(0.0, |t| (t.as_secs_f32() / f.config.spawn_timer.as_secs_f32()).min(1.0));
+
+    [
+        if p == Position::WALL { 1.0 } else { 0.0 },
+        if p == Position::UNKNOWN || p.is_suspected() { 1.0 } else { 0.0 },
+        if matches!(p, Position::SAFE | Position::EMPTY | Position::GOLD | Position::POWERUP) { 1.0 } else { 0.0 },
+        h.hole as f32,
+        h.teleport as f32,
+        ready(f.gold_positions.get(c)),
+        ready(f.powerup_positions.get(c)),
+    ]
+}
@@ -3,14 +3,17 @@ pub mod data;
 
 use crate::api::{
     graphics::{
-        data::{Data, BotInfo, FieldInfo},
+        data::{Data, BotInfo, FieldInfo, ScoreInfo},
         connection::Connection,
     },
     bot::BotData,
     ai::AI,
-    map::{Field}
+    map::{Field, query}
 };
 
+/// Enemy probabilities below this are not drawn
+const MIN_ENEMY: f64 = 0.05;
+
 pub struct Graphics {
     connection: Connection,
     ident: String,
@@ -41,9 +44,16 @@ impl Graphics {
             energy: bot.get_energy(),
             score: bot.get_score(),
             state: ai.current_state.to_string(),
+            scores: ai.utility_scores.iter().map(|s| ScoreInfo {
+                behaviour: s.behaviour.to_string(),
+                score: s.score,
+                considerations: s.considerations.iter().map(|(i, v)| (i.to_string(), *v)).collect(),
+            }).collect(),
+            coverage: query::coverage(field),
+            trace: ai.trace.summary(),
         };
 
-        let map = field.map.iter().map(|(c, p)| {
+        let map = field.grid.known().map(|(c, p)| {
             (c.x, c.y, p.to_string())
         }).collect();
         let gold = field.gold_positions.iter().map(|(c, p)| {
@@ -52,18 +62,27 @@ impl Graphics {
         let powerup = field.powerup_positions.iter().map(|(c, p)| {
             (c.x, c.y, p.as_millis() as i64)
         }).collect();
-        let midpoint = (field.buffer_midpoint_coord.x, field.buffer_midpoint_coord.y);
+        let frontier = ai.frontier.iter().map(|c| (c.x, c.y)).collect();
         let current_path: Vec<(i16, i16)> = match &ai.current_path {
             Some(p) => p.coords.iter().map(|c| {(c.x, c.y)}).collect(),
             None => vec![]
         };
+        let teleports = field.teleports.keys().filter_map(|c| {
+            query::teleport_exit(field, c).map(|e| (c.x, c.y, e.x, e.y))
+        }).collect();
+
+        let enemies = field.enemies.above(MIN_ENEMY).iter().map(|(c, p)| (c.x, c.y, *p)).collect();
 
         let fieldinfo = FieldInfo {
             map,
             gold,
             powerup,
-            midpoint,
-            current_path
+            frontier,
+            current_path,
+            teleports,
+            enemies,
+            width: field.width(),
+            height: field.height(),
         };
 
 
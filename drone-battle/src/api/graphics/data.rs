@@ -15,7 +15,21 @@ pub struct BotInfo {
     pub dir: String,
     pub energy: i32,
     pub score: i64,
-    pub state: String
+    pub state: String,
+    /// Score of each behaviour, when using the utility AI
+    pub scores: Vec<ScoreInfo>,
+    /// Percentage of the map already known
+    pub coverage: f64,
+    /// Summary of the decision trace of the last tick
+    pub trace: String
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ScoreInfo {
+    pub behaviour: String,
+    pub score: f64,
+    /// Input of each consideration, with the value of its curve
+    pub considerations: Vec<(String, f64)>
 }
 
 #[derive(Serialize, Deserialize)]
@@ -23,7 +37,14 @@ pub struct FieldInfo {
     pub map: Vec<(i16, i16, String)>,
     pub powerup: Vec<(i16, i16, i64)>,
     pub gold: Vec<(i16, i16, i64)>,
-    pub midpoint: (i16, i16),
-    pub current_path: Vec<(i16, i16)>
+    /// Frontier blocks of the last exploration
+    pub frontier: Vec<(i16, i16)>,
+    pub current_path: Vec<(i16, i16)>,
+    pub teleports: Vec<(i16, i16, i16, i16)>,
+    /// Blocks where an enemy probably is, with the probability
+    pub enemies: Vec<(i16, i16, f64)>,
+    /// Size of the map, in blocks
+    pub width: i16,
+    pub height: i16,
 }
 
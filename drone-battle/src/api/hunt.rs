@@ -0,0 +1,78 @@
+use crate::api::enums::{Action, PlayerDirection};
+use crate::api::map::{Coord, Field};
+use crate::api::map::query;
+use crate::api::structs::Scoreboard;
+
+use std::collections::HashMap;
+
+/// Ticks between two scoreboards asked while playing
+pub const SCOREBOARD_TICKS: i32 = 10;
+/// Farthest block, in steps, where a hunted enemy is looked for
+const HUNT_RADIUS: u8 = 8;
+/// Least chance of an enemy in a block to go after it
+const MIN_TARGET_BELIEF: f64 = 0.2;
+/// Least chance of an enemy in the line of fire to shoot without seeing it
+pub const SHOOT_BELIEF: f64 = 0.5;
+/// Extra cost of walking into a block, for each point of chance of some enemy seeing it
+pub const COVER_COST: f64 = 5.0;
+
+/// Energy of each opponent, from the scoreboard and the hits of the bot
+pub struct Opponents {
+    energy: HashMap<String, i32>,
+}
+
+impl Opponents {
+    pub fn new() -> Opponents {
+        Opponents { energy: HashMap::new() }
+    }
+
+    /// Replaces what is known with the scoreboard. Players offline and the bot itself are left out
+    pub fn update(&mut self, scoreboards: &[Scoreboard], me: &str) {
+        self.energy = scoreboards.iter()
+            .filter(|s| s.connected && s.name != me)
+            .map(|s| (s.name.clone(), s.energy))
+            .collect();
+    }
+
+    /// The bot hit `target`, which lost some energy since the last scoreboard
+    pub fn hit(&mut self, target: &str, damage: i32) {
+        if let Some(e) = self.energy.get_mut(target) { *e = (*e - damage).max(0) }
+    }
+
+    /// The weakest opponent alive with `max_energy` or less, and its energy
+    pub fn weakened(&self, max_energy: f64) -> Option<(&String, i32)> {
+        self.energy.iter()
+            .filter(|(_, e)| **e > 0 && **e as f64 <= max_energy)
+            .min_by_key(|(_, e)| **e)
+            .map(|(n, e)| (n, *e))
+    }
+
+    pub fn clear(&mut self) { self.energy.clear(); }
+}
+
+impl Default for Opponents {
+    fn default() -> Self { Opponents::new() }
+}
+
+/// First turn to face `to`, when facing `dir`
+pub fn turn_to(dir: &PlayerDirection, to: &PlayerDirection) -> Action {
+    if dir.left() == *to { Action::LEFT } else { Action::RIGHT }
+}
+
+/// Block near `c` where an enemy most probably is, if the chance is big enough to go after it
+pub fn target(f: &Field, c: &Coord) -> Option<(Coord, f64)> {
+    // the belief does not tell which enemy is where, so any enemy nearby is taken as the weakened one
+    let r = HUNT_RADIUS as i16;
+    let mut best: Option<(Coord, f64)> = None;
+    for x in (c.x - r)..=(c.x + r) {
+        for y in (c.y - r)..=(c.y + r) {
+            let b = Coord { x, y };
+            if b == *c || b.manhattan(c) > HUNT_RADIUS as u16 { continue }
+            let p = query::enemy(f, &b);
+            if p < MIN_TARGET_BELIEF { continue }
+            let closer = |(bc, bp): &(Coord, f64)| p > *bp || (p == *bp && b.manhattan(c) < bc.manhattan(c));
+            if best.as_ref().is_none_or(closer) { best = Some((b, p)) }
+        }
+    }
+    best
+}
@@ -3,12 +3,28 @@ pub mod query;
 pub mod update;
 pub mod node;
 pub mod path;
+pub mod inference;
+pub mod route;
+pub mod frontier;
+pub mod pose;
+pub mod enemies;
+pub mod threat;
+pub mod grid;
+pub mod distance;
+pub mod dstar;
+pub mod text;
 
 use crate::api::enums::PlayerDirection;
+use crate::api::map::inference::Inference;
+use crate::api::map::enemies::EnemyBelief;
+use crate::api::map::grid::Grid;
+use crate::api::map::distance::DistanceField;
 use crate::Config;
 
 use std::time::Duration;
 use std::collections::HashMap;
+use std::cell::RefCell;
+use std::rc::Rc;
 use std::fmt;
 
 #[derive(Hash, Eq, PartialEq, Debug, Clone, PartialOrd, Ord)]
@@ -18,11 +34,6 @@ pub struct Coord {
 }
 
 impl Coord {
-    pub fn add(&mut self, c: &Coord) {
-        self.x += c.x;
-        self.y += c.y;
-    }
-
     pub fn next(&self, dir: &PlayerDirection) -> Coord {
         match dir {
             PlayerDirection::NORTH => Coord { x: self.x, y: self.y - 1 },
@@ -41,10 +52,14 @@ impl fmt::Display for Coord {
 
 
 #[derive(PartialEq, Debug, Clone)]
+#[allow(non_camel_case_types)]
 pub enum Position {
     SAFE,
     EMPTY,
-    DANGER,
+    HOLE,
+    TELEPORT,
+    SUSPECTED_HOLE,
+    SUSPECTED_TELEPORT,
     UNKNOWN,
     WALL,
     GOLD,
@@ -56,100 +71,172 @@ impl Position {
         match self {
             Position::SAFE => "SAFE".to_string(),
             Position::EMPTY => "EMPTY".to_string(),
-            Position::DANGER => "DANGER".to_string(),
+            Position::HOLE => "HOLE".to_string(),
+            Position::TELEPORT => "TELEPORT".to_string(),
+            Position::SUSPECTED_HOLE => "SUSPECTED_HOLE".to_string(),
+            Position::SUSPECTED_TELEPORT => "SUSPECTED_TELEPORT".to_string(),
             Position::UNKNOWN => "UNKNOWN".to_string(),
             Position::WALL => "WALL".to_string(),
             Position::GOLD => "GOLD".to_string(),
             Position::POWERUP => "POWERUP".to_string(),
         }
     }
+
+    pub fn from_str(s: &str) -> Option<Position> {
+        match s {
+            "SAFE" => Some(Position::SAFE),
+            "EMPTY" => Some(Position::EMPTY),
+            "HOLE" => Some(Position::HOLE),
+            "TELEPORT" => Some(Position::TELEPORT),
+            "SUSPECTED_HOLE" => Some(Position::SUSPECTED_HOLE),
+            "SUSPECTED_TELEPORT" => Some(Position::SUSPECTED_TELEPORT),
+            "UNKNOWN" => Some(Position::UNKNOWN),
+            "WALL" => Some(Position::WALL),
+            "GOLD" => Some(Position::GOLD),
+            "POWERUP" => Some(Position::POWERUP),
+            _ => None
+        }
+    }
+
+    /// One character for each kind of block, to draw the map as text
+    pub fn to_char(&self) -> char {
+        match self {
+            Position::SAFE => ':',
+            Position::EMPTY => '.',
+            Position::HOLE => 'O',
+            Position::TELEPORT => 'T',
+            Position::SUSPECTED_HOLE => 'o',
+            Position::SUSPECTED_TELEPORT => 't',
+            Position::UNKNOWN => '?',
+            Position::WALL => '#',
+            Position::GOLD => '$',
+            Position::POWERUP => '+',
+        }
+    }
+
+    pub fn from_char(c: char) -> Option<Position> {
+        match c {
+            ':' => Some(Position::SAFE),
+            '.' => Some(Position::EMPTY),
+            'O' => Some(Position::HOLE),
+            'T' => Some(Position::TELEPORT),
+            'o' => Some(Position::SUSPECTED_HOLE),
+            't' => Some(Position::SUSPECTED_TELEPORT),
+            '?' => Some(Position::UNKNOWN),
+            '#' => Some(Position::WALL),
+            '$' => Some(Position::GOLD),
+            '+' => Some(Position::POWERUP),
+            _ => None
+        }
+    }
+
+    /// Holes and teleports, confirmed or not
+    pub fn is_hazard(&self) -> bool {
+        self.is_suspected() || *self == Position::HOLE || *self == Position::TELEPORT
+    }
+
+    /// Hazards that were not confirmed yet
+    pub fn is_suspected(&self) -> bool {
+        *self == Position::SUSPECTED_HOLE || *self == Position::SUSPECTED_TELEPORT
+    }
 }
 
+/// Size of the map of the game server, when the config does not tell another one
 pub const MAP_WIDTH: i16 = 59;
 pub const MAP_HEIGHT: i16 = 34;
+/// Largest size of the map, as given by the config or inferred, as the coordinates of other players cannot be trusted
+pub const MAX_INFERRED_SIZE: i16 = 256;
 
 
 pub struct Field {
-    pub map: HashMap<Coord, Position>,
+    /// What each block is, and if it is safe or in a firing lane
+    pub grid: Grid,
     pub gold_positions: HashMap<Coord, Duration>,
     pub powerup_positions: HashMap<Coord, Duration>,
-    pub safe_positions: HashMap<Coord, bool>,
-    pub unsafe_positions: HashMap<Coord, i32>,
+    /// Every exit seen for each teleport
+    pub teleports: HashMap<Coord, Vec<Coord>>,
+    /// Teammate that told about each block not seen by the bot itself
+    pub provenance: HashMap<Coord, String>,
+    /// Golds that some teammate is going after, and who
+    pub reserved: HashMap<Coord, String>,
     pub config: Config,
     pub spawn: Option<Coord>,
-
-    pub buffer_midpoint_size: usize,
-    pub buffer_midpoint_coord: Coord,
-
+    pub inference: Inference,
+    /// Where the enemies probably are
+    pub enemies: EnemyBelief,
+    /// Costs from the pose of the bot, searched once a tick
+    pub distances: RefCell<Option<Rc<DistanceField>>>,
 }
 
 impl Field {
     pub fn new(config: &Config) -> Field {
         Field {
-            map: HashMap::new(),
+            grid: Grid::new(config.map_width, config.map_height),
             gold_positions: HashMap::new(),
             powerup_positions: HashMap::new(),
-            safe_positions: HashMap::new(),
-            unsafe_positions: HashMap::new(),
+            teleports: HashMap::new(),
+            provenance: HashMap::new(),
+            reserved: HashMap::new(),
             spawn: None,
+            inference: Inference::new(),
+            enemies: EnemyBelief::new(config.map_width, config.map_height),
+            distances: RefCell::new(None),
             config: config.clone(),
-            buffer_midpoint_size: 0,
-            buffer_midpoint_coord: Coord {x: 0, y: 0}
         }
     }
 
     pub fn set_spawn(&mut self, c: &Coord) {
         self.spawn = Some(c.clone());
-        self.buffer_midpoint_coord = c.clone();
+    }
+
+    pub fn width(&self) -> i16 { self.grid.width() }
+
+    pub fn height(&self) -> i16 { self.grid.height() }
+
+    /// Someone was seen at `c`, or the bot bumped into a wall there, so the map goes at least that far.
+    /// Grows the map to fit it, if the size is inferred.
+    ///
+    /// The edge of the map blocks the bot as a wall does, so a wall just past the border may leave the map a block
+    /// bigger than it is. Positions past `MAX_INFERRED_SIZE` are ignored. Returns if the map grew
+    pub fn fit(&mut self, c: &Coord) -> bool {
+        if !self.config.infer_map_size || c.x < 0 || c.y < 0 { return false }
+        if c.x < self.width() && c.y < self.height() { return false }
+
+        let (width, height) = match (c.x.checked_add(1), c.y.checked_add(1)) {
+            (Some(w), Some(h)) if w <= MAX_INFERRED_SIZE && h <= MAX_INFERRED_SIZE => {
+                (self.width().max(w), self.height().max(h))
+            },
+            _ => return false
+        };
+        self.grid.resize(width, height);
+        self.enemies.resize(width, height);
+        self.distances.replace(None);
+        true
     }
 
     pub fn restart(&mut self) {
-        self.map.clear();
+        self.grid.clear();
         self.gold_positions.clear();
         self.powerup_positions.clear();
-        self.safe_positions.clear();
-        self.unsafe_positions.clear();
+        self.teleports.clear();
+        self.provenance.clear();
+        self.reserved.clear();
+        self.inference.clear();
+        self.enemies.clear();
+        self.distances.replace(None);
         self.spawn = None;
-        self.buffer_midpoint_size = 0;
-        self.buffer_midpoint_coord = Coord {x: 0, y: 0}
     }
 }
 
 impl Coord {
-    pub fn manhattan(&self, c2: &Coord) -> u8 {
-        ((self.x as i16 - c2.x as i16).abs() + (self.y as i16 - c2.y as i16).abs()) as u8
+    pub fn manhattan(&self, c2: &Coord) -> u16 {
+        self.x.abs_diff(c2.x).saturating_add(self.y.abs_diff(c2.y))
     }
 
-    pub fn coords_5x2_sides(&self, dir: &PlayerDirection) -> Vec<Coord> {
-        match dir {
-            PlayerDirection::NORTH | PlayerDirection::SOUTH => vec![
-                Coord { x: self.x-2, y: self.y-2 }, Coord { x: self.x-1, y: self.y-2 },
-                Coord { x: self.x+2, y: self.y-2 }, Coord { x: self.x+1, y: self.y-2 },
-                Coord { x: self.x-2, y: self.y-1 }, Coord { x: self.x-1, y: self.y-1 },
-                Coord { x: self.x+2, y: self.y-1 }, Coord { x: self.x+1, y: self.y-1 },
-                Coord { x: self.x-2, y: self.y }, Coord { x: self.x-1, y: self.y },
-                Coord { x: self.x+2, y: self.y }, Coord { x: self.x+1, y: self.y },
-                Coord { x: self.x-2, y: self.y+1 }, Coord { x: self.x-1, y: self.y+1 },
-                Coord { x: self.x+2, y: self.y+1 }, Coord { x: self.x+1, y: self.y+1 },
-                Coord { x: self.x-2, y: self.y+2 }, Coord { x: self.x-1, y: self.y+2 },
-                Coord { x: self.x+2, y: self.y+2 }, Coord { x: self.x+1, y: self.y+2 },
-            ],
-            _ => vec![
-                Coord {x: self.x-2, y: self.y-2}, Coord {x: self.x-2, y: self.y-1},
-                Coord {x: self.x-2, y: self.y+2}, Coord {x: self.x-2, y: self.y+1},
-
-                Coord {x: self.x-1, y: self.y-2}, Coord {x: self.x-1, y: self.y-1},
-                Coord {x: self.x-1, y: self.y+2}, Coord {x: self.x-1, y: self.y+1},
-
-                Coord {x: self.x, y: self.y-2}, Coord {x: self.x, y: self.y-1},
-                Coord {x: self.x, y: self.y+2}, Coord {x: self.x, y: self.y+1},
-
-                Coord {x: self.x+1, y: self.y-2}, Coord {x: self.x-2, y: self.y-1},
-                Coord {x: self.x+1, y: self.y+2}, Coord {x: self.x-2, y: self.y+1},
-
-                Coord {x: self.x+2, y: self.y-2}, Coord {x: self.x-2, y: self.y-1},
-                Coord {x: self.x+2, y: self.y+2}, Coord {x: self.x-2, y: self.y+1},
-            ]
-        }
+    pub fn around(&self) -> Vec<Coord> {
+        vec![
+            Coord { x: self.x + 1, y: self.y }, Coord { x: self.x - 1, y: self.y },
+            Coord { x: self.x, y: self.y + 1 }, Coord { x: self.x, y: self.y - 1 },
+        ]
     }
 }
\ No newline at end of file
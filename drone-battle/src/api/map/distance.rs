@@ -0,0 +1,222 @@
+use crate::api::enums::PlayerDirection;
+use crate::api::map::{Coord, Field};
+use crate::api::map::node::Node;
+use crate::api::map::path::Path;
+
+use ordered_float::OrderedFloat;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::rc::Rc;
+
+/// Cost and path from one pose of the bot to every block it can reach, with the same costs as the A*
+pub struct DistanceField {
+    origin: Node,
+    width: i16,
+    height: i16,
+    // cheapest cost to arrive at each pose, infinite if it cannot be reached
+    costs: Vec<f64>,
+    // pose before each pose, in the cheapest way to arrive there
+    parents: Vec<Option<usize>>,
+}
+
+impl DistanceField {
+    pub fn new(f: &Field, c: &Coord, dir: &PlayerDirection) -> DistanceField {
+        let (width, height) = (f.width(), f.height());
+        let len = width.max(0) as usize * height.max(0) as usize * 4;
+        let mut d = DistanceField {
+            origin: Node { coord: c.clone(), dir: dir.clone() },
+            width,
+            height,
+            costs: vec![f64::INFINITY; len],
+            parents: vec![None; len],
+        };
+        let start = match d.index(&d.origin) {
+            Some(i) => i,
+            None => return d,
+        };
+
+        let mut queue: BinaryHeap<Reverse<(OrderedFloat<f64>, usize)>> = BinaryHeap::new();
+        d.costs[start] = 0.0;
+        queue.push(Reverse((OrderedFloat(0.0), start)));
+        while let Some(Reverse((cost, i))) = queue.pop() {
+            if cost.0 > d.costs[i] { continue }     // already arrived here cheaper
+            for (n, step) in d.node(i).neighbours(f) {
+                let j = match d.index(&n) {
+                    Some(j) => j,
+                    None => continue,
+                };
+                let next = cost.0 + step.0;
+                if next < d.costs[j] {
+                    d.costs[j] = next;
+                    d.parents[j] = Some(i);
+                    queue.push(Reverse((OrderedFloat(next), j)));
+                }
+            }
+        }
+        d
+    }
+
+    /// Where the search started from
+    pub fn origin(&self) -> &Node { &self.origin }
+
+    /// Cheapest cost to arrive at `dest`, in any direction, if it can be reached
+    pub fn cost(&self, dest: &Coord) -> Option<f64> {
+        self.arrival(dest).map(|i| self.costs[i])
+    }
+
+    /// Cheapest path to `dest`, if it can be reached
+    pub fn path(&self, dest: &Coord) -> Option<Path> {
+        let mut i = self.arrival(dest)?;
+        let mut nodes: Vec<Node> = vec![self.node(i)];
+        while let Some(p) = self.parents[i] {
+            nodes.push(self.node(p));
+            i = p;
+        }
+        nodes.reverse();
+        Path::from_nodes(nodes)
+    }
+
+    fn arrival(&self, dest: &Coord) -> Option<usize> {
+        Node::poses(dest).iter()
+            .filter_map(|n| self.index(n))
+            .filter(|i| self.costs[*i].is_finite())
+            .min_by(|a, b| self.costs[*a].total_cmp(&self.costs[*b]))
+    }
+
+    fn index(&self, n: &Node) -> Option<usize> { n.index(self.width, self.height) }
+
+    fn node(&self, i: usize) -> Node { Node::from_index(i, self.height) }
+}
+
+/// Distance field from the pose of the bot, searched again only from another pose or after `forget`
+pub fn from(f: &Field, c: &Coord, dir: &PlayerDirection) -> Rc<DistanceField> {
+    let origin = Node { coord: c.clone(), dir: dir.clone() };
+    if let Some(d) = f.distances.borrow().as_ref() {
+        if d.origin == origin { return Rc::clone(d) }
+    }
+    let d = Rc::new(DistanceField::new(f, c, dir));
+    *f.distances.borrow_mut() = Some(Rc::clone(&d));
+    d
+}
+
+/// The field changed, so the cached distances are not valid anymore
+pub fn forget(f: &Field) { *f.distances.borrow_mut() = None; }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::config::Config;
+    use crate::api::map::{logic, query, text, Position};
+
+    use pathfinding::prelude::astar;
+
+    /// Rooms with safe blocks and a firing lane, so that steps cost differently
+    const ROOMS: &str = "\
+##########
+#>..#..$.#
+#.#.#.##.#
+#.#+++...#
+#.####.#.#
+#..!!!...#
+##########
+";
+
+    /// A single corridor, so there is only one cheapest path
+    const CORRIDOR: &str = "\
+#######
+#>...##
+####.##
+##...##
+##.####
+##...$#
+#######
+";
+
+    /// The gold is only reached through the teleport
+    const TELEPORT: &str = "\
+#######
+#>.T#.#
+#######
+#...$.#
+#######
+teleport 3 1 1 3
+";
+
+    fn field(s: &str) -> (Field, Node) {
+        let m = text::parse(s, &Config::default()).expect("a valid map");
+        (m.field, m.bot.expect("drawn by its arrow"))
+    }
+
+    /// Cost of the path `logic::a_star` finds, searching the same way
+    fn a_star_cost(f: &Field, bot: &Node, dest: &Coord) -> Option<f64> {
+        let p = astar(bot, |n: &Node| n.neighbours(f), |n: &Node| n.distance_to_goal(f, dest), |n: &Node| n.coord == *dest);
+        p.map(|(_, cost)| cost.0)
+    }
+
+    fn floor(f: &Field) -> Vec<Coord> {
+        (0..f.width()).flat_map(|x| (0..f.height()).map(move |y| Coord { x, y }))
+            .filter(|c| query::get(f, c) != Position::WALL)
+            .collect()
+    }
+
+    #[test]
+    fn same_cost_as_a_star() {
+        let (f, bot) = field(ROOMS);
+        let d = DistanceField::new(&f, &bot.coord, &bot.dir);
+        for c in floor(&f) {
+            let cost = d.cost(&c).expect("every floor is reachable");
+            let expected = a_star_cost(&f, &bot, &c).expect("a path");
+            assert!((cost - expected).abs() < 1e-9, "{:?}: {} != {}", c, cost, expected);
+
+            let path = d.path(&c);
+            let a_star = logic::a_star(&f, &bot.coord, &bot.dir, &c);
+            assert_eq!(path.as_ref().map(|p| p.dest.clone()), a_star.as_ref().map(|p| p.dest.clone()));
+        }
+    }
+
+    #[test]
+    fn same_path_as_a_star() {
+        let (f, bot) = field(CORRIDOR);
+        let d = DistanceField::new(&f, &bot.coord, &bot.dir);
+        for c in [Coord { x: 4, y: 1 }, Coord { x: 2, y: 3 }, Coord { x: 5, y: 5 }] {
+            let path = d.path(&c).expect("a path along the corridor");
+            let a_star = logic::a_star(&f, &bot.coord, &bot.dir, &c).expect("a path along the corridor");
+            assert_eq!(path.coords, a_star.coords);
+            assert_eq!(path.actions, a_star.actions);
+            assert_eq!(path.dest_dir, a_star.dest_dir);
+        }
+    }
+
+    #[test]
+    fn unreachable_targets() {
+        let (f, bot) = field(TELEPORT);
+        let d = DistanceField::new(&f, &bot.coord, &bot.dir);
+        for c in [Coord { x: 5, y: 1 }, Coord { x: 4, y: 1 }, Coord { x: 20, y: 1 }, Coord { x: -1, y: 0 }] {
+            assert_eq!(d.cost(&c), None, "{:?}", c);
+            assert!(d.path(&c).is_none());
+            assert!(logic::a_star(&f, &bot.coord, &bot.dir, &c).is_none());
+        }
+    }
+
+    #[test]
+    fn through_a_teleport() {
+        let (f, bot) = field(TELEPORT);
+        let d = DistanceField::new(&f, &bot.coord, &bot.dir);
+        let gold = Coord { x: 4, y: 3 };
+
+        let path = d.path(&gold).expect("a way through the teleport");
+        assert!(path.coords.contains(&Coord { x: 1, y: 3 }), "the exit is in the path");
+        let cost = d.cost(&gold).expect("a way through the teleport");
+        let expected = a_star_cost(&f, &bot, &gold).expect("a way through the teleport");
+        assert!((cost - expected).abs() < 1e-9);
+
+        let a_star = logic::a_star(&f, &bot.coord, &bot.dir, &gold).expect("a way through the teleport");
+        assert_eq!(path.coords, a_star.coords);
+        assert_eq!(path.actions, a_star.actions);
+
+        // without the exit, the teleport is only a wall
+        let mut lost = f;
+        lost.teleports.clear();
+        assert_eq!(DistanceField::new(&lost, &bot.coord, &bot.dir).cost(&gold), None);
+    }
+}
@@ -0,0 +1,335 @@
+use crate::api::enums::PlayerDirection;
+use crate::api::map::{Coord, Field, Position};
+use crate::api::map::node::Node;
+use crate::api::map::path::Path;
+use crate::api::map::query;
+
+use ordered_float::OrderedFloat;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+/// Priority of a pose in the queue, the smallest first
+type Key = (OrderedFloat<f64>, OrderedFloat<f64>);
+
+/// What the cost of walking into a block depends on
+#[derive(Clone, PartialEq)]
+struct Block {
+    position: Position,
+    safe: bool,
+    danger: f64,
+    hole: f64,
+    teleport: f64,
+    exit: Option<Coord>,
+}
+
+impl Block {
+    fn read(f: &Field, c: &Coord) -> Block {
+        let h = query::hazard(f, c);
+        Block {
+            position: query::get(f, c),
+            safe: query::is_safe(f, c),
+            danger: query::danger(f, c),
+            hole: h.hole,
+            teleport: h.teleport,
+            exit: query::teleport_exit(f, c),
+        }
+    }
+}
+
+/// Path to one block, kept between ticks and repaired only where the map changed (D* Lite)
+pub struct DStarLite {
+    goal: Coord,
+    width: i16,
+    height: i16,
+    // least cost of a step between blocks, for the heuristic. 0 with teleports, as they jump anywhere
+    step: f64,
+    start: Node,
+    // pose of the bot in the last search, that the heuristic in the keys of the queue is from
+    last: Node,
+    // how much the heuristic went down since the first search, added to the new keys
+    km: f64,
+    // cost to the goal of each pose, when it was last expanded
+    g: Vec<f64>,
+    // cost to the goal of each pose, from the costs of its neighbours
+    rhs: Vec<f64>,
+    // key of each pose in the queue, if it is there. Older entries of the heap are skipped
+    queued: Vec<Option<Key>>,
+    queue: BinaryHeap<Reverse<(Key, usize)>>,
+    blocks: Vec<Block>,
+    // position in the log of changes of the grid, at the last search
+    seen: usize,
+}
+
+impl DStarLite {
+    pub fn new(f: &Field, goal: &Coord, c: &Coord, dir: &PlayerDirection) -> DStarLite {
+        let (width, height) = (f.width(), f.height());
+        let len = width.max(0) as usize * height.max(0) as usize * 4;
+        let start = Node { coord: c.clone(), dir: dir.clone() };
+        let mut d = DStarLite {
+            goal: goal.clone(),
+            width,
+            height,
+            step: min_step(f),
+            start: start.clone(),
+            last: start,
+            km: 0.0,
+            g: vec![f64::INFINITY; len],
+            rhs: vec![f64::INFINITY; len],
+            queued: vec![None; len],
+            queue: BinaryHeap::new(),
+            blocks: (0..width)
+                .flat_map(|x| (0..height).map(move |y| Coord { x, y }))
+                .map(|c| Block::read(f, &c))
+                .collect(),
+            seen: f.grid.changes_end(),
+        };
+        for n in Node::poses(goal) {
+            if let Some(i) = d.index(&n) {
+                d.rhs[i] = 0.0;
+                d.push(i);
+            }
+        }
+        d
+    }
+
+    pub fn goal(&self) -> &Coord { &self.goal }
+
+    /// Path from the pose of the bot to the goal, searching again only where the map changed since the last one
+    pub fn path(&mut self, f: &Field, c: &Coord, dir: &PlayerDirection) -> Option<Path> {
+        // the map grew or a teleport was found: the costs kept are of another graph
+        if f.width() != self.width || f.height() != self.height || min_step(f) != self.step {
+            *self = DStarLite::new(f, &self.goal, c, dir);
+        }
+
+        self.start = Node { coord: c.clone(), dir: dir.clone() };
+        // the search goes on from the new pose even if nothing changed, so the keys are from it
+        self.km += self.h(&self.last, &self.start);
+        self.last = self.start.clone();
+        let changed = self.changed(f);
+        if !changed.is_empty() {
+            // the cost of walking into a block changes the poses in it and next to it,
+            // and next to the entries of the teleports that lead to it
+            for b in changed.iter() {
+                let entries: Vec<Coord> = f.teleports.keys()
+                    .filter(|e| query::teleport_exit(f, e).as_ref() == Some(b))
+                    .cloned()
+                    .collect();
+                let blocks = std::iter::once(b.clone()).chain(b.around()).chain(entries.iter().flat_map(|e| e.around()));
+                for n in blocks.flat_map(|a| Node::poses(&a)) {
+                    self.update(f, &n);
+                }
+            }
+        }
+        self.search(f);
+        self.walk(f)
+    }
+
+    // every block is compared if the log does not go back that far
+    fn changed(&mut self, f: &Field) -> Vec<Coord> {
+        let logged: Vec<Coord> = match f.grid.changes_since(self.seen) {
+            Some(changes) => changes.to_vec(),
+            None => (0..self.width).flat_map(|x| (0..self.height).map(move |y| Coord { x, y })).collect(),
+        };
+        self.seen = f.grid.changes_end();
+
+        let mut ret: Vec<Coord> = Vec::new();
+        for c in logged {
+            let i = c.x as usize * self.height as usize + c.y as usize;
+            let current = Block::read(f, &c);
+            if current != self.blocks[i] {
+                self.blocks[i] = current;
+                ret.push(c);
+            }
+        }
+        ret
+    }
+
+    fn search(&mut self, f: &Field) {
+        let start = match self.index(&self.start) {
+            Some(s) => s,
+            None => return,
+        };
+        while let Some((old, u)) = self.top() {
+            if old >= self.key(start) && self.rhs[start] <= self.g[start] { break }
+            self.queue.pop();
+            self.queued[u] = None;
+
+            let node = self.node(u);
+            if old < self.key(u) {
+                self.push(u);       // the heuristic went down since it was queued
+            } else if self.g[u] > self.rhs[u] {
+                self.g[u] = self.rhs[u];
+                for p in self.predecessors(f, &node) { self.update(f, &p) }
+            } else {
+                self.g[u] = f64::INFINITY;
+                self.update(f, &node);
+                for p in self.predecessors(f, &node) { self.update(f, &p) }
+            }
+        }
+    }
+
+    fn walk(&self, f: &Field) -> Option<Path> {
+        let mut current = self.start.clone();
+        // the bot itself may be left unexpanded, once its cost from the neighbours is certain
+        if self.rhs[self.index(&current)?].is_infinite() { return None }
+
+        let mut nodes: Vec<Node> = vec![current.clone()];
+        while current.coord != self.goal {
+            if nodes.len() > self.g.len() { return None }     // only if the costs are broken
+            let next = current.neighbours(f).into_iter()
+                .filter_map(|(n, cost)| { let g = self.g[self.index(&n)?]; Some((n, cost.0 + g)) })
+                .filter(|(_, cost)| cost.is_finite())
+                .min_by(|a, b| a.1.total_cmp(&b.1))
+                .map(|(n, _)| n)?;
+            nodes.push(next.clone());
+            current = next;
+        }
+        Path::from_nodes(nodes)
+    }
+
+    // queues the pose if its cost does not match the last expansion
+    fn update(&mut self, f: &Field, n: &Node) {
+        let i = match self.index(n) {
+            Some(i) => i,
+            None => return,
+        };
+        if n.coord != self.goal {
+            self.rhs[i] = n.neighbours(f).into_iter()
+                .filter_map(|(s, cost)| self.index(&s).map(|j| cost.0 + self.g[j]))
+                .fold(f64::INFINITY, f64::min);
+        }
+        if self.g[i] != self.rhs[i] { self.push(i) } else { self.queued[i] = None }
+    }
+
+    // walking front or back, turning, or through a teleport that leads to `n`
+    fn predecessors(&self, f: &Field, n: &Node) -> Vec<Node> {
+        let mut ret: Vec<Node> = vec![
+            Node { coord: n.coord.next(&n.dir.opposite()), dir: n.dir.clone() },
+            Node { coord: n.coord.next(&n.dir), dir: n.dir.clone() },
+            Node { coord: n.coord.clone(), dir: n.dir.left() },
+            Node { coord: n.coord.clone(), dir: n.dir.right() },
+        ];
+        for entry in f.teleports.keys() {
+            if query::teleport_exit(f, entry).as_ref() == Some(&n.coord) {
+                ret.push(Node { coord: entry.next(&n.dir.opposite()), dir: n.dir.clone() });
+            }
+        }
+        ret
+    }
+
+    // skips the entries of poses queued again since then
+    fn top(&mut self) -> Option<(Key, usize)> {
+        while let Some(Reverse((k, i))) = self.queue.peek() {
+            if self.queued[*i] == Some(*k) { return Some((*k, *i)) }
+            self.queue.pop();
+        }
+        None
+    }
+
+    fn push(&mut self, i: usize) {
+        let k = self.key(i);
+        self.queued[i] = Some(k);
+        self.queue.push(Reverse((k, i)));
+    }
+
+    fn key(&self, i: usize) -> Key {
+        let cost = self.g[i].min(self.rhs[i]);
+        (OrderedFloat(cost + self.h(&self.start, &self.node(i)) + self.km), OrderedFloat(cost))
+    }
+
+    fn h(&self, a: &Node, b: &Node) -> f64 { self.step * a.coord.manhattan(&b.coord) as f64 }
+
+    fn index(&self, n: &Node) -> Option<usize> { n.index(self.width, self.height) }
+
+    fn node(&self, i: usize) -> Node { Node::from_index(i, self.height) }
+}
+
+/// Least cost of a step to another block, so the heuristic never goes over the real cost
+fn min_step(f: &Field) -> f64 {
+    if !f.teleports.is_empty() { return 0.0 }
+    let p = &f.config.params;
+    let front = p.safe_bonus.min(1.0);
+    front.min((1.0 + p.back_penalty) * front).max(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::config::Config;
+    use crate::api::map::distance::DistanceField;
+    use crate::api::map::update;
+
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    const GOAL: Coord = Coord { x: 10, y: 7 };
+
+    fn random_block(rng: &mut StdRng) -> Coord {
+        Coord { x: rng.gen_range(0..12), y: rng.gen_range(0..9) }
+    }
+
+    /// Changes a random block: a wall, floor, a safe block, or a teleport to another block
+    fn change(f: &mut Field, rng: &mut StdRng, c: &Coord) {
+        match rng.gen_range(0..5) {
+            0 => update::set(f, c.clone(), Position::WALL, true),
+            1 => update::set(f, c.clone(), Position::EMPTY, true),
+            2 => { update::set(f, c.clone(), Position::EMPTY, true); update::set_safe(f, c.clone()) },
+            3 => update::remove_safe(f, c),
+            _ => {
+                let exit = random_block(rng);
+                if exit != *c && *c != GOAL { update::set_teleport(f, c.clone(), exit) }
+            },
+        }
+    }
+
+    #[test]
+    fn repairs_like_a_new_search() {
+        let mut config = Config::default();
+        config.map_width = 12;
+        config.map_height = 9;
+        let mut f = Field::new(&config);
+        let mut rng = StdRng::seed_from_u64(7);
+        for x in 0..12 {
+            for y in 0..9 {
+                let p = if rng.gen_bool(0.2) { Position::WALL } else { Position::EMPTY };
+                update::set(&mut f, Coord { x, y }, p, true);
+            }
+        }
+        update::set(&mut f, GOAL, Position::EMPTY, true);
+        update::set_teleport(&mut f, Coord { x: 3, y: 3 }, Coord { x: 9, y: 6 });
+
+        let mut c = Coord { x: 1, y: 1 };
+        let mut dir = PlayerDirection::EAST;
+        let mut d = DStarLite::new(&f, &GOAL, &c, &dir);
+        for round in 0..300 {
+            // the exits of the teleports are changed more often, as their costs spread through the entries
+            for _ in 0..rng.gen_range(1..4) {
+                let exit = f.teleports.values().flatten().next().cloned();
+                let b = match exit {
+                    Some(e) if rng.gen_bool(0.3) => e,
+                    _ => random_block(&mut rng),
+                };
+                if b != GOAL && b != c { change(&mut f, &mut rng, &b) }
+            }
+            if rng.gen_bool(0.3) {
+                let next = random_block(&mut rng);
+                if query::get(&f, &next) == Position::EMPTY && next != GOAL {
+                    c = next;
+                    dir = PlayerDirection::all()[rng.gen_range(0..4)].clone();
+                }
+            }
+
+            let path = d.path(&f, &c, &dir);
+            let expected = DistanceField::new(&f, &c, &dir).cost(&GOAL);
+            let start = d.index(&d.start).unwrap();
+            match expected {
+                None => assert!(path.is_none(), "round {}: found a path that does not exist", round),
+                Some(cost) => {
+                    assert_eq!(path.map(|p| p.dest), Some(GOAL), "round {}: no path", round);
+                    assert!((d.g[start].min(d.rhs[start]) - cost).abs() < 1e-9,
+                        "round {}: cost {} instead of {}", round, d.rhs[start], cost);
+                },
+            }
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use crate::api::enums::PlayerDirection;
+use crate::api::map::{Coord, Field, Position};
+use crate::api::map::query;
+use crate::api::structs::LastObservation;
+
+/// Chance of an enemy being in a block nothing is known about
+pub const ENEMY_PRIOR: f64 = 0.002;
+/// Blocks an enemy sees (and shoots) in front of it
+pub const VIEW_RANGE: i16 = 10;
+/// Chance of an enemy leaving its block in a tick
+const MOVE_RATE: f64 = 0.3;
+/// How much of the belief goes back to the prior each tick
+const DECAY: f64 = 0.02;
+/// What is left of the belief where a percept says there is no enemy, as the percepts may be late
+const MISS: f64 = 0.1;
+
+/// Chance of an enemy being in each block of the map, spread as they walk and sharpened by the percepts
+pub struct EnemyBelief {
+    width: i16,
+    height: i16,
+    cells: Vec<f64>,
+}
+
+impl EnemyBelief {
+    pub fn new(width: i16, height: i16) -> EnemyBelief {
+        EnemyBelief { width, height, cells: vec![ENEMY_PRIOR; (width.max(0) as usize) * (height.max(0) as usize)] }
+    }
+
+    /// Changes the size of the map. New blocks start with the prior
+    pub fn resize(&mut self, width: i16, height: i16) {
+        let mut belief = EnemyBelief::new(width, height);
+        for (i, p) in self.cells.iter().enumerate() {
+            belief.set(&self.coord(i), *p);
+        }
+        *self = belief;
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.iter_mut().for_each(|p| *p = ENEMY_PRIOR);
+    }
+
+    pub fn get(&self, c: &Coord) -> f64 {
+        self.index(c).map_or(0.0, |i| self.cells[i])
+    }
+
+    fn set(&mut self, c: &Coord, p: f64) {
+        if let Some(i) = self.index(c) { self.cells[i] = p.clamp(0.0, 1.0) }
+    }
+
+    /// Blocks where an enemy is more likely than `min`
+    pub fn above(&self, min: f64) -> Vec<(Coord, f64)> {
+        self.cells.iter().enumerate()
+            .filter(|(_, p)| **p > min)
+            .map(|(i, p)| (self.coord(i), *p))
+            .collect()
+    }
+
+    fn index(&self, c: &Coord) -> Option<usize> {
+        if c.x < 0 || c.y < 0 || c.x >= self.width || c.y >= self.height { None }
+        else { Some(c.y as usize * self.width as usize + c.x as usize) }
+    }
+
+    fn coord(&self, i: usize) -> Coord {
+        Coord { x: (i % self.width as usize) as i16, y: (i / self.width as usize) as i16 }
+    }
+}
+
+fn is_open(f: &Field, c: &Coord) -> bool {
+    !matches!(query::get(f, c), Position::WALL | Position::HOLE)
+}
+
+/// Blocks in front of `c`, up to the view range or the first wall
+pub fn line(f: &Field, c: &Coord, dir: &PlayerDirection) -> Vec<Coord> { query::visible(f, c, dir, VIEW_RANGE) }
+
+/// Moves the belief one tick forward: the enemies may walk to the blocks around
+pub fn predict(f: &mut Field) {
+    let mut next: Vec<f64> = vec![0.0; f.enemies.cells.len()];
+    for (i, p) in f.enemies.cells.iter().enumerate() {
+        if *p <= 0.0 { continue }
+        let c = f.enemies.coord(i);
+        let around: Vec<usize> = c.around().iter()
+            .filter(|n| is_open(f, n))
+            .filter_map(|n| f.enemies.index(n))
+            .collect();
+        if around.is_empty() { next[i] += p; continue }
+
+        next[i] += p * (1.0 - MOVE_RATE);
+        for n in around.iter() { next[*n] += p * MOVE_RATE / around.len() as f64 }
+    }
+    for (i, p) in next.iter_mut().enumerate() {
+        *p = if is_open(f, &f.enemies.coord(i)) { (*p + DECAY * (ENEMY_PRIOR - *p)).min(1.0) } else { 0.0 };
+    }
+    f.enemies.cells = next;
+}
+
+/// Updates the belief with what the bot sensed at `c`, facing `dir`
+pub fn observe(f: &mut Field, c: &Coord, dir: &PlayerDirection, o: &LastObservation) {
+    f.enemies.set(c, 0.0);
+
+    // steps: some enemy is right next to the bot
+    let around: Vec<Coord> = c.around().into_iter().filter(|n| is_open(f, n)).collect();
+    if o.is_steps { at_least_one(f, &around) } else { scale(f, &around, MISS) }
+
+    // enemy in front: exactly where it is, and nobody before it
+    let front = line(f, c, dir);
+    if o.is_enemy_front && o.distance_enemy_front > 0 {
+        let d = o.distance_enemy_front as usize;
+        scale(f, &front[..(d - 1).min(front.len())], MISS);
+        if let Some(e) = front.get(d - 1) { f.enemies.set(e, 1.0) }
+    } else if o.is_hit {
+        at_least_one(f, &front);
+    } else {
+        scale(f, &front, MISS);
+    }
+
+    // damage: some enemy is in line with the bot
+    if o.is_damage && !o.is_enemy_front {
+        let lines: Vec<Coord> = PlayerDirection::all().iter().flat_map(|d| line(f, c, d)).collect();
+        at_least_one(f, &lines);
+    }
+}
+
+/// Some player, that is not the bot, was told to be at `c`
+pub fn seen(f: &mut Field, c: &Coord) { f.enemies.set(c, 1.0) }
+
+/// A teammate is at `c`, so no enemy is
+pub fn absent(f: &mut Field, c: &Coord) { f.enemies.set(c, 0.0) }
+
+/// Each block keeps its chance, given that at least one of `cells` has an enemy
+fn at_least_one(f: &mut Field, cells: &[Coord]) {
+    if cells.is_empty() { return }
+    let none: f64 = cells.iter().map(|c| 1.0 - f.enemies.get(c)).product();
+    let some = 1.0 - none;
+    for c in cells {
+        // the percept contradicts the belief, every block gets the same chance
+        let p = if some > 0.0 { f.enemies.get(c) / some } else { 1.0 / cells.len() as f64 };
+        f.enemies.set(c, p);
+    }
+}
+
+fn scale(f: &mut Field, cells: &[Coord], k: f64) {
+    for c in cells { f.enemies.set(c, f.enemies.get(c) * k) }
+}
@@ -0,0 +1,141 @@
+use crate::api::enums::PlayerDirection;
+use crate::api::map::{Coord, Field, Position};
+use crate::api::map::query;
+use crate::api::map::path::Path;
+use crate::api::map::distance;
+
+use std::collections::{BTreeSet, HashSet, VecDeque};
+
+/// Steps worth walking for each block revealed
+const GAIN_WEIGHT: f64 = 1.0;
+/// How much the hazard probability around a cluster takes from its gain
+const RISK_WEIGHT: f64 = 2.0;
+
+/// Blocks of the frontier next to each other, explored as one
+pub struct Cluster {
+    pub blocks: Vec<Coord>,
+    // expected number of blocks revealed by exploring the cluster
+    pub gain: f64,
+    // sum of the hazard probabilities of the unknown blocks around
+    pub risk: f64,
+}
+
+/// Closest block of a cluster, as scored by `best_path`
+pub struct Target {
+    pub coord: Coord,
+    // cost of the path to the block
+    pub cost: f64,
+    pub value: f64,
+}
+
+/// Safe blocks not explored yet, the only places worth going to when exploring
+pub fn blocks(f: &Field) -> Vec<Coord> {
+    f.grid.safe().collect()
+}
+
+/// Groups the frontier into clusters of blocks next to each other, sorted by their blocks
+pub fn clusters(f: &Field) -> Vec<Cluster> {
+    let mut starts = blocks(f);
+    starts.sort();
+    let frontier: HashSet<Coord> = starts.iter().cloned().collect();
+    let mut seen: HashSet<Coord> = HashSet::new();
+    let mut ret: Vec<Cluster> = Vec::new();
+
+    for start in starts.iter() {
+        if !seen.insert(start.clone()) { continue }
+
+        let mut cluster: Vec<Coord> = Vec::new();
+        let mut queue: VecDeque<Coord> = VecDeque::from([start.clone()]);
+        while let Some(c) = queue.pop_front() {
+            for n in c.around() {
+                if frontier.contains(&n) && seen.insert(n.clone()) { queue.push_back(n) }
+            }
+            cluster.push(c);
+        }
+        cluster.sort();
+        ret.push(score(f, cluster));
+    }
+    ret
+}
+
+fn score(f: &Field, blocks: Vec<Coord>) -> Cluster {
+    // in order, so the sums are the same every time
+    let mut revealed: BTreeSet<Coord> = BTreeSet::new();
+    for c in blocks.iter() {
+        revealed.insert(c.clone());
+        revealed.extend(c.around());
+    }
+
+    let (mut gain, mut risk) = (0.0, 0.0);
+    for c in revealed.iter() {
+        let p = query::get(f, c);
+        let unrevealed = query::is_safe(f, c) || p == Position::UNKNOWN || p.is_suspected();
+        if !unrevealed { continue }
+        let h = query::hazard(f, c);
+        gain += 1.0 - h.hole - h.teleport;
+        risk += h.hole + h.teleport;
+    }
+    Cluster { blocks, gain, risk }
+}
+
+/// Path to the best cluster, by its gain minus its risk and the cost to reach it, and the closest block of each
+pub fn best_path(f: &Field, c: &Coord, dir: &PlayerDirection, clusters: &[Cluster]) -> (Option<Path>, Vec<Target>) {
+    let distances = distance::from(f, c, dir);
+
+    let mut best: Option<(&Coord, f64)> = None;
+    let mut targets: Vec<Target> = Vec::new();
+    for cluster in clusters.iter() {
+        let closest = cluster.blocks.iter()
+            .filter_map(|b| Some((b, distances.cost(b)?)))
+            .min_by(|a, b| a.1.total_cmp(&b.1).then_with(|| a.0.cmp(b.0)));
+        let (block, cost) = match closest {
+            Some(x) => x,
+            None => continue,
+        };
+        let value = GAIN_WEIGHT * (cluster.gain - RISK_WEIGHT * cluster.risk) - cost;
+        // ties go to the smallest block, so the same field always gives the same path
+        let better = |(b, v): (&Coord, f64)| value > v || (value == v && block < b);
+        if best.is_none_or(better) { best = Some((block, value)) }
+        targets.push(Target { coord: block.clone(), cost, value });
+    }
+
+    let path = best.and_then(|(target, _)| distances.path(target));
+    (path, targets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::config::Config;
+    use crate::api::map::text;
+
+    /// Two frontier blocks at the same cost and with the same gain, one on each side of the bot
+    const TIE: &str = "\
+#######
+#:.^.:#
+#.....#
+#######
+";
+
+    #[test]
+    fn same_clusters_and_path_every_time() {
+        let m = text::parse(TIE, &Config::default()).expect("a valid map");
+        let bot = m.bot.expect("drawn by its arrow");
+        let f = &m.field;
+
+        let first = clusters(f);
+        let starts: Vec<&Coord> = first.iter().map(|cl| &cl.blocks[0]).collect();
+        assert_eq!(starts, vec![&Coord { x: 1, y: 1 }, &Coord { x: 5, y: 1 }]);
+
+        let (path, targets) = best_path(f, &bot.coord, &bot.dir, &first);
+        assert_eq!(targets.len(), 2);
+        assert_eq!(targets[0].value, targets[1].value);
+        assert_eq!(path.map(|p| p.dest), Some(Coord { x: 1, y: 1 }));
+
+        for _ in 0..10 {
+            let again = clusters(f);
+            let blocks: Vec<&Vec<Coord>> = again.iter().map(|cl| &cl.blocks).collect();
+            assert_eq!(blocks, first.iter().map(|cl| &cl.blocks).collect::<Vec<_>>());
+        }
+    }
+}
@@ -0,0 +1,264 @@
+use crate::api::map::{Coord, Position};
+
+/// Changes kept in the log
+const MAX_CHANGES: usize = 4096;
+
+#[derive(Clone)]
+struct Cell {
+    position: Position,
+    // danger of the firing lanes over the block, 0 if none
+    danger: f32,
+}
+
+// one bit for each block of the map
+#[derive(Clone)]
+struct Bits {
+    words: Vec<u64>,
+}
+
+impl Bits {
+    fn new(len: usize) -> Bits { Bits { words: vec![0; len.div_ceil(64)] } }
+
+    fn get(&self, i: usize) -> bool { self.words[i / 64] & (1 << (i % 64)) != 0 }
+
+    fn set(&mut self, i: usize, value: bool) {
+        if value { self.words[i / 64] |= 1 << (i % 64) } else { self.words[i / 64] &= !(1 << (i % 64)) }
+    }
+
+    fn count(&self) -> usize { self.words.iter().map(|w| w.count_ones() as usize).sum() }
+
+    // skips 64 blocks at a time where there is none
+    fn ones(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words.iter().enumerate().flat_map(|(w, word)| {
+            let mut word = *word;
+            std::iter::from_fn(move || {
+                if word == 0 { return None }
+                let bit = word.trailing_zeros() as usize;
+                word &= word - 1;
+                Some(w * 64 + bit)
+            })
+        })
+    }
+
+    fn clear(&mut self) { self.words.iter_mut().for_each(|w| *w = 0) }
+}
+
+/// What the bot knows about each block, indexed by `x * height + y`. Blocks outside the map are walls
+pub struct Grid {
+    width: i16,
+    height: i16,
+    cells: Vec<Cell>,
+    safe: Bits,
+    unsafe_blocks: Bits,
+    changes: Vec<Coord>,
+    // changes dropped from the start of the log
+    base: usize,
+}
+
+impl Grid {
+    pub fn new(width: i16, height: i16) -> Grid {
+        let len = (width.max(0) as usize) * (height.max(0) as usize);
+        Grid {
+            width,
+            height,
+            cells: vec![Cell { position: Position::UNKNOWN, danger: 0.0 }; len],
+            safe: Bits::new(len),
+            unsafe_blocks: Bits::new(len),
+            changes: Vec::new(),
+            base: 0,
+        }
+    }
+
+    pub fn width(&self) -> i16 { self.width }
+
+    pub fn height(&self) -> i16 { self.height }
+
+    fn index(&self, c: &Coord) -> Option<usize> {
+        if c.x < 0 || c.y < 0 || c.x >= self.width || c.y >= self.height { None }
+        else { Some(c.x as usize * self.height as usize + c.y as usize) }
+    }
+
+    fn coord(&self, i: usize) -> Coord {
+        Coord { x: (i / self.height as usize) as i16, y: (i % self.height as usize) as i16 }
+    }
+
+    pub fn get(&self, c: &Coord) -> Position {
+        match self.index(c) {
+            Some(i) => self.cells[i].position.clone(),
+            None => Position::WALL,
+        }
+    }
+
+    pub fn set(&mut self, c: &Coord, p: Position) {
+        if let Some(i) = self.index(c) {
+            if self.cells[i].position != p { self.log(c.clone()) }
+            self.cells[i].position = p;
+        }
+    }
+
+    pub fn known(&self) -> impl Iterator<Item = (Coord, &Position)> + '_ {
+        self.cells.iter().enumerate()
+            .filter(|(_, cell)| cell.position != Position::UNKNOWN)
+            .map(|(i, cell)| (self.coord(i), &cell.position))
+    }
+
+    pub fn is_safe(&self, c: &Coord) -> bool { self.index(c).is_some_and(|i| self.safe.get(i)) }
+
+    pub fn set_safe(&mut self, c: &Coord, safe: bool) {
+        if let Some(i) = self.index(c) {
+            if self.safe.get(i) != safe { self.log(c.clone()) }
+            self.safe.set(i, safe);
+        }
+    }
+
+    /// Safe blocks not explored yet
+    pub fn safe(&self) -> impl Iterator<Item = Coord> + '_ { self.safe.ones().map(|i| self.coord(i)) }
+
+    pub fn safe_count(&self) -> usize { self.safe.count() }
+
+    pub fn is_unsafe(&self, c: &Coord) -> bool { self.index(c).is_some_and(|i| self.unsafe_blocks.get(i)) }
+
+    pub fn danger(&self, c: &Coord) -> f64 { self.index(c).map_or(0.0, |i| self.cells[i].danger as f64) }
+
+    /// Sets the danger of a block, where 0 makes it not unsafe anymore
+    pub fn set_danger(&mut self, c: &Coord, danger: f64) {
+        if let Some(i) = self.index(c) {
+            if self.cells[i].danger != danger.max(0.0) as f32 { self.log(c.clone()) }
+            self.cells[i].danger = danger.max(0.0) as f32;
+            self.unsafe_blocks.set(i, danger > 0.0);
+        }
+    }
+
+    pub fn unsafe_count(&self) -> usize { self.unsafe_blocks.count() }
+
+    /// Multiplies the danger of every unsafe block by `k`, and forgets the ones left under `min`
+    pub fn decay(&mut self, k: f64, min: f64) {
+        let unsafe_blocks: Vec<usize> = self.unsafe_blocks.ones().collect();
+        for i in unsafe_blocks {
+            let danger = self.cells[i].danger as f64 * k;
+            self.cells[i].danger = if danger >= min { danger as f32 } else { 0.0 };
+            self.unsafe_blocks.set(i, danger >= min);
+            // a bit less danger is not worth searching again, only the block that is not unsafe anymore
+            if danger < min { self.log(self.coord(i)) }
+        }
+    }
+
+    /// Position in the log after the last change
+    pub fn changes_end(&self) -> usize { self.base + self.changes.len() }
+
+    /// Blocks changed since `start`, or `None` if the log does not go that far back anymore
+    pub fn changes_since(&self, start: usize) -> Option<&[Coord]> {
+        if start < self.base || start > self.changes_end() { return None }
+        Some(&self.changes[start - self.base..])
+    }
+
+    /// Logs a change not in the grid, as the hazards of the block or the exit of its teleport
+    pub fn touch(&mut self, c: &Coord) {
+        if self.index(c).is_some() { self.log(c.clone()) }
+    }
+
+    fn log(&mut self, c: Coord) {
+        if self.changes.len() >= MAX_CHANGES {
+            self.changes.drain(..MAX_CHANGES / 2);
+            self.base += MAX_CHANGES / 2;
+        }
+        self.changes.push(c);
+    }
+
+    /// Changes the size of the map, keeping what is known about the blocks still inside it
+    pub fn resize(&mut self, width: i16, height: i16) {
+        let mut grid = Grid::new(width, height);
+        for (i, cell) in self.cells.iter().enumerate() {
+            let c = self.coord(i);
+            if let Some(j) = grid.index(&c) {
+                grid.cells[j] = cell.clone();
+                grid.safe.set(j, self.safe.get(i));
+                grid.unsafe_blocks.set(j, self.unsafe_blocks.get(i));
+            }
+        }
+        grid.base = self.changes_end() + 1;     // every block is taken as changed
+        *self = grid;
+    }
+
+    pub fn clear(&mut self) {
+        for cell in self.cells.iter_mut() { *cell = Cell { position: Position::UNKNOWN, danger: 0.0 } }
+        self.safe.clear();
+        self.unsafe_blocks.clear();
+        self.base = self.changes_end() + 1;     // every block is taken as changed
+        self.changes.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn index_and_coord() {
+        let g = Grid::new(7, 5);
+        for i in 0..35 {
+            assert_eq!(g.index(&g.coord(i)), Some(i));
+        }
+        for c in [Coord { x: -1, y: 0 }, Coord { x: 0, y: -1 }, Coord { x: 7, y: 0 }, Coord { x: 0, y: 5 }] {
+            assert_eq!(g.index(&c), None);
+            assert_eq!(g.get(&c), Position::WALL);
+        }
+    }
+
+    #[test]
+    fn resize_keeps_blocks() {
+        let mut g = Grid::new(4, 3);
+        let (a, b) = (Coord { x: 1, y: 2 }, Coord { x: 3, y: 0 });
+        g.set(&a, Position::WALL);
+        g.set_safe(&b, true);
+        g.set_danger(&a, 0.5);
+
+        g.resize(6, 5);
+        assert_eq!(g.get(&a), Position::WALL);
+        assert!(g.is_safe(&b) && g.is_unsafe(&a));
+        assert_eq!(g.danger(&a), 0.5);
+        assert_eq!(g.get(&Coord { x: 5, y: 4 }), Position::UNKNOWN);
+        assert_eq!((g.safe_count(), g.unsafe_count()), (1, 1));
+
+        // blocks left outside are dropped
+        g.resize(3, 5);
+        assert_eq!(g.get(&a), Position::WALL);
+        assert_eq!(g.safe_count(), 0);
+    }
+
+    #[test]
+    fn changes_since_a_drop_or_clear() {
+        let mut g = Grid::new(4, 4);
+        let c = Coord { x: 1, y: 1 };
+        let start = g.changes_end();
+        g.set(&c, Position::WALL);
+        assert_eq!(g.changes_since(start), Some(&[c.clone()][..]));
+
+        // the log drops its older half when full
+        for i in 0..MAX_CHANGES { g.touch(&Coord { x: (i % 4) as i16, y: 0 }) }
+        assert_eq!(g.changes_since(start), None);
+        let end = g.changes_end();
+        assert_eq!(g.changes_since(end), Some(&[][..]));
+
+        g.clear();
+        assert_eq!(g.changes_since(end), None);
+        let end = g.changes_end();
+        g.set(&c, Position::EMPTY);
+        assert_eq!(g.changes_since(end), Some(&[c][..]));
+    }
+
+    #[test]
+    fn decay_logs_the_blocks_left_safe() {
+        let mut g = Grid::new(4, 4);
+        let (a, b) = (Coord { x: 0, y: 0 }, Coord { x: 2, y: 3 });
+        g.set_danger(&a, 1.0);
+        g.set_danger(&b, 0.2);
+        let start = g.changes_end();
+
+        g.decay(0.5, 0.15);
+        assert_eq!(g.changes_since(start), Some(&[b.clone()][..]));
+        assert!(g.is_unsafe(&a) && !g.is_unsafe(&b));
+        assert_eq!(g.danger(&a), 0.5);
+        assert_eq!(g.danger(&b), 0.0);
+    }
+}
@@ -0,0 +1,379 @@
+use crate::api::map::{Coord, Field, Position};
+use crate::api::map::{query, update};
+
+use std::collections::{HashMap, HashSet};
+
+/// Probability of an unexplored block hiding a hazard, before any observation
+pub const HAZARD_PRIOR: f64 = 0.05;
+
+/// Biggest group of blocks where every combination of hazards is counted
+const MAX_ENUMERATION: usize = 12;
+
+/// Hazards that can only be sensed from an adjacent block
+#[derive(Hash, Eq, PartialEq, Debug, Clone, Copy)]
+pub enum Hazard {
+    HOLE,       // sensed as a breeze
+    TELEPORT,   // sensed as a flash
+}
+
+impl Hazard {
+    pub fn all() -> [Hazard; 2] { [Hazard::HOLE, Hazard::TELEPORT] }
+}
+
+/// What was sensed while standing in some block
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct Percept {
+    breeze: bool,
+    flash: bool,
+}
+
+impl Percept {
+    fn senses(&self, h: Hazard) -> bool {
+        match h {
+            Hazard::HOLE => self.breeze,
+            Hazard::TELEPORT => self.flash,
+        }
+    }
+}
+
+/// Probability of each hazard being in a block
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct HazardBelief {
+    pub hole: f64,
+    pub teleport: f64,
+}
+
+impl HazardBelief {
+    pub fn get(&self, h: Hazard) -> f64 {
+        match h {
+            Hazard::HOLE => self.hole,
+            Hazard::TELEPORT => self.teleport,
+        }
+    }
+
+    fn set(&mut self, h: Hazard, p: f64) {
+        match h {
+            Hazard::HOLE => self.hole = p,
+            Hazard::TELEPORT => self.teleport = p,
+        }
+    }
+
+    // holes come first, as they are lethal
+    pub fn position(&self) -> Position {
+        if self.hole >= 1.0 { Position::HOLE }
+        else if self.teleport >= 1.0 { Position::TELEPORT }
+        else if self.hole >= self.teleport { Position::SUSPECTED_HOLE }
+        else { Position::SUSPECTED_TELEPORT }
+    }
+}
+
+/// Percept history of a match, and the hazards deduced from it
+pub struct Inference {
+    percepts: HashMap<Coord, Percept>,
+    pub beliefs: HashMap<Coord, HazardBelief>,
+    pub safe: HashSet<Coord>,
+    // groups solved in the last run, so the ones no new percept touched are not solved again
+    solved: HashMap<Vec<Vec<Coord>>, Vec<f64>>,
+}
+
+impl Inference {
+    pub fn new() -> Inference {
+        Inference {
+            percepts: HashMap::new(),
+            beliefs: HashMap::new(),
+            safe: HashSet::new(),
+            solved: HashMap::new(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.percepts.clear();
+        self.beliefs.clear();
+        self.safe.clear();
+        self.solved.clear();
+    }
+
+    pub fn has_visited(&self, c: &Coord) -> bool { self.percepts.contains_key(c) }
+
+    pub fn belief(&self, c: &Coord) -> Option<&HazardBelief> { self.beliefs.get(c) }
+}
+
+/// Registers what was sensed at `c`, and returns `true` if the field has changed
+pub fn observe(f: &mut Field, c: &Coord, breeze: bool, flash: bool) -> bool {
+    // a percept, once sensed, stays there for the whole match
+    let p = f.inference.percepts.entry(c.clone()).or_default();
+    p.breeze |= breeze;
+    p.flash |= flash;
+
+    solve(f);
+    apply(f)
+}
+
+fn solve(f: &mut Field) {
+    let mut beliefs: HashMap<Coord, HazardBelief> = HashMap::new();
+    let mut safe: Option<HashSet<Coord>> = None;
+    let last = std::mem::take(&mut f.inference.solved);
+    let mut solved = HashMap::new();
+
+    for h in Hazard::all() {
+        let free = solve_hazard(f, h, &mut beliefs, &last, &mut solved);
+        // a block is safe only if it is free of every hazard
+        safe = Some(match safe {
+            None => free,
+            Some(s) => s.intersection(&free).cloned().collect()
+        });
+    }
+
+    // the hazards of these blocks changed, even where the map does not show it
+    let old = &f.inference.beliefs;
+    let touched: Vec<Coord> = beliefs.iter()
+        .filter(|(c, b)| old.get(c) != Some(b))
+        .map(|(c, _)| c.clone())
+        .chain(old.keys().filter(|c| !beliefs.contains_key(c)).cloned())
+        .collect();
+    for c in touched.iter() { f.grid.touch(c) }
+
+    f.inference.beliefs = beliefs;
+    f.inference.safe = safe.unwrap_or_default();
+    f.inference.solved = solved;
+}
+
+// returns the blocks proven to be free of the hazard
+fn solve_hazard(
+    f: &Field, h: Hazard, beliefs: &mut HashMap<Coord, HazardBelief>,
+    last: &HashMap<Vec<Vec<Coord>>, Vec<f64>>, solved: &mut HashMap<Vec<Vec<Coord>>, Vec<f64>>
+) -> HashSet<Coord> {
+    let (free, constraints) = candidates(f, h);
+    let definite = definite(&constraints);
+    for c in definite.iter() {
+        beliefs.entry(c.clone()).or_default().set(h, 1.0);
+    }
+
+    // percepts already explained by a certain hazard do not say anything about their other candidates
+    let (unsatisfied, satisfied): (Vec<Vec<Coord>>, Vec<Vec<Coord>>) = constraints.into_iter()
+        .partition(|cands| !cands.iter().any(|c| definite.contains(c)));
+    for c in satisfied.iter().flatten() {
+        if !definite.contains(c) {
+            beliefs.entry(c.clone()).or_default().set(h, HAZARD_PRIOR);
+        }
+    }
+
+    for group in components(&unsatisfied) {
+        // the same percepts in any order, and their blocks in the order they first appear
+        let mut group: Vec<Vec<Coord>> = group.into_iter()
+            .map(|cands| { let mut cands = cands.clone(); cands.sort(); cands })
+            .collect();
+        group.sort();
+        let mut cells: Vec<Coord> = Vec::new();
+        for c in group.iter().flatten() {
+            if !cells.contains(c) { cells.push(c.clone()) }
+        }
+
+        let ps = match last.get(&group).or_else(|| solved.get(&group)) {
+            Some(ps) => ps.clone(),
+            None => probabilities(&cells, &group.iter().collect::<Vec<&Vec<Coord>>>()),
+        };
+        for (c, p) in cells.iter().zip(ps.iter()) {
+            beliefs.entry(c.clone()).or_default().set(h, *p);
+        }
+        solved.insert(group, ps);
+    }
+
+    free
+}
+
+/// Candidates of each percept of the hazard that no certain hazard explains yet
+pub fn unexplained(f: &Field, h: Hazard) -> Vec<Vec<Coord>> {
+    let (_, constraints) = candidates(f, h);
+    let definite = definite(&constraints);
+    constraints.into_iter().filter(|cands| !cands.iter().any(|c| definite.contains(c))).collect()
+}
+
+fn candidates(f: &Field, h: Hazard) -> (HashSet<Coord>, Vec<Vec<Coord>>) {
+    let percepts = &f.inference.percepts;
+
+    // the bot survived (and was not teleported) in every visited block,
+    // and no block around a block without the percept can have the hazard
+    let mut free: HashSet<Coord> = percepts.keys().cloned().collect();
+    for (c, p) in percepts.iter() {
+        if !p.senses(h) { free.extend(c.around()); }
+    }
+
+    // every percept needs at least one hazard between its candidates
+    let constraints: Vec<Vec<Coord>> = percepts.iter()
+        .filter(|(_, p)| p.senses(h))
+        .map(|(c, _)| {
+            c.around().into_iter()
+                .filter(|n| !free.contains(n) && query::get(f, n) != Position::WALL)
+                .collect::<Vec<Coord>>()
+        })
+        .filter(|cands| !cands.is_empty())      // contradiction, probably a stale observation
+        .collect();
+    (free, constraints)
+}
+
+// blocks that are the only explanation for some percept
+fn definite(constraints: &[Vec<Coord>]) -> HashSet<Coord> {
+    constraints.iter()
+        .filter(|cands| cands.len() == 1)
+        .map(|cands| cands[0].clone())
+        .collect()
+}
+
+// groups of constraints that share no block between them
+fn components(constraints: &[Vec<Coord>]) -> Vec<Vec<&Vec<Coord>>> {
+    let mut by_cell: HashMap<&Coord, Vec<usize>> = HashMap::new();
+    for (i, cands) in constraints.iter().enumerate() {
+        for c in cands { by_cell.entry(c).or_default().push(i); }
+    }
+
+    let mut seen: Vec<bool> = vec![false; constraints.len()];
+    let mut ret = Vec::new();
+    for start in 0..constraints.len() {
+        if seen[start] { continue }
+        seen[start] = true;
+
+        let mut stack = vec![start];
+        let mut cells: Vec<Coord> = Vec::new();
+        let mut group: Vec<&Vec<Coord>> = Vec::new();
+        while let Some(i) = stack.pop() {
+            group.push(&constraints[i]);
+            for c in constraints[i].iter() {
+                if cells.contains(c) { continue }
+                cells.push(c.clone());
+                for &j in by_cell.get(c).unwrap() {
+                    if !seen[j] {
+                        seen[j] = true;
+                        stack.push(j);
+                    }
+                }
+            }
+        }
+        ret.push(group);
+    }
+    ret
+}
+
+fn probabilities(cells: &[Coord], constraints: &[&Vec<Coord>]) -> Vec<f64> {
+    // too many combinations, each block gets the chance of being the only explanation
+    if cells.len() > MAX_ENUMERATION {
+        return cells.iter().map(|c| {
+            constraints.iter()
+                .filter(|cands| cands.contains(c))
+                .map(|cands| 1.0 / cands.len() as f64)
+                .fold(HAZARD_PRIOR, f64::max)
+        }).collect();
+    }
+
+    let masks: Vec<u32> = constraints.iter().map(|cands| {
+        cells.iter().enumerate()
+            .filter(|(_, c)| cands.contains(c))
+            .fold(0, |m, (i, _)| m | (1 << i))
+    }).collect();
+
+    let n = cells.len() as i32;
+    let mut total = 0.0;
+    let mut marginals = vec![0.0; cells.len()];
+    for m in 0u32..(1 << n) {
+        if masks.iter().any(|cm| cm & m == 0) { continue }     // some percept without explanation
+        let k = m.count_ones() as i32;
+        let w = HAZARD_PRIOR.powi(k) * (1.0 - HAZARD_PRIOR).powi(n - k);
+        total += w;
+        for (i, mg) in marginals.iter_mut().enumerate() {
+            if m & (1 << i) != 0 { *mg += w }
+        }
+    }
+
+    marginals.iter().map(|mg| if total > 0.0 { mg / total } else { HAZARD_PRIOR }).collect()
+}
+
+fn apply(f: &mut Field) -> bool {
+    let mut changed = false;
+
+    let safe: Vec<Coord> = f.inference.safe.iter()
+        .filter(|c| !f.inference.has_visited(c))
+        .cloned()
+        .collect();
+    for c in safe {
+        let current = query::get(f, &c);
+        if current == Position::UNKNOWN || current.is_suspected() {
+            update::set(f, c, Position::SAFE, false);
+            changed = true;
+        }
+    }
+
+    let danger: Vec<(Coord, Position)> = f.inference.beliefs.iter()
+        .map(|(c, b)| (c.clone(), b.position()))
+        .collect();
+    for (c, p) in danger {
+        let current = query::get(f, &c);
+        if current != p && (current == Position::UNKNOWN || current.is_suspected()) {
+            update::set(f, c, p, false);
+            changed = true;
+        }
+    }
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::config::Config;
+
+    fn field(breezes: &[(i16, i16)], calm: &[(i16, i16)]) -> Field {
+        let mut f = Field::new(&Config::default());
+        for (x, y) in breezes { observe(&mut f, &Coord { x: *x, y: *y }, true, false); }
+        for (x, y) in calm { observe(&mut f, &Coord { x: *x, y: *y }, false, false); }
+        f
+    }
+
+    fn hole(f: &Field, x: i16, y: i16) -> f64 {
+        f.inference.belief(&Coord { x, y }).map_or(0.0, |b| b.hole)
+    }
+
+    #[test]
+    fn only_candidate_is_a_hole() {
+        // the calm blocks prove (5, 4), (4, 5) and (6, 5) safe, so the breeze can only come from (5, 6)
+        let f = field(&[(5, 5)], &[(4, 4), (6, 4)]);
+        for c in [Coord { x: 5, y: 4 }, Coord { x: 4, y: 5 }, Coord { x: 6, y: 5 }] {
+            assert!(f.inference.safe.contains(&c), "{:?} should be safe", c);
+        }
+        assert_eq!(hole(&f, 5, 6), 1.0);
+        assert_eq!(query::get(&f, &Coord { x: 5, y: 6 }), Position::HOLE);
+    }
+
+    #[test]
+    fn overlapping_breezes() {
+        // (6, 5) explains both breezes, so it is the most likely hole
+        let f = field(&[(5, 5), (7, 5)], &[]);
+        let shared = hole(&f, 6, 5);
+        let side = hole(&f, 4, 5);
+        assert!(shared > side && shared < 1.0, "shared {} side {}", shared, side);
+        assert!(side > HAZARD_PRIOR);
+        assert!((side - hole(&f, 8, 5)).abs() < 1e-9);
+        assert!((side - hole(&f, 5, 4)).abs() < 1e-9);
+        assert_eq!(f.inference.solved.len(), 1);
+    }
+
+    #[test]
+    fn big_groups_are_approximated() {
+        // a row of breezes, two blocks apart, chains 19 blocks into one group
+        let breezes: Vec<(i16, i16)> = (1..=6).map(|i| (2 * i, 5)).collect();
+        let f = field(&breezes, &[]);
+        let cells: Vec<Coord> = breezes.iter()
+            .flat_map(|(x, y)| Coord { x: *x, y: *y }.around())
+            .collect();
+        assert!(cells.len() > MAX_ENUMERATION);
+        // each block gets the chance of being the only explanation of a breeze with 4 candidates
+        for c in cells { assert_eq!(hole(&f, c.x, c.y), 0.25, "{:?}", c); }
+    }
+
+    #[test]
+    fn order_does_not_matter() {
+        let a = field(&[(5, 5), (7, 5), (20, 20)], &[(4, 4)]);
+        let mut b = field(&[(20, 20), (7, 5)], &[(4, 4)]);
+        observe(&mut b, &Coord { x: 5, y: 5 }, true, false);
+        assert_eq!(a.inference.beliefs, b.inference.beliefs);
+        assert_eq!(a.inference.safe, b.inference.safe);
+    }
+}
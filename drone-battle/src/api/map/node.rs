@@ -6,6 +6,16 @@ use crate::api::map::query;
 
 use ordered_float::OrderedFloat;
 
+/// Blocks with a bigger chance of having a hole are never used in a path
+pub const MAX_HOLE_PROBABILITY: f64 = 0.2;
+/// Teleports are not lethal, so the bot can risk a bit more
+pub const MAX_TELEPORT_PROBABILITY: f64 = 0.35;
+/// Cost of walking into a block that certainly has a hole
+pub const HOLE_COST: f64 = 20.0;
+/// Cost of walking into a block that certainly has a teleport with an unknown exit
+pub const TELEPORT_RISK_COST: f64 = 10.0;
+/// Cost of walking into a teleport with a known exit
+pub const TELEPORT_COST: f64 = 5.0;
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialOrd, PartialEq)]
 pub struct Node {
@@ -15,16 +25,50 @@ pub struct Node {
 
 impl Node {
     // using https://docs.rs/pathfinding/0.5.3/pathfinding/fn.astar.html
-    pub fn distance_to_goal(&self, goal: &Coord) -> OrderedFloat<f64> {
-        OrderedFloat(self.coord.manhattan(goal) as f64)
+    /// The 4 poses of a block
+    pub fn poses(c: &Coord) -> Vec<Node> {
+        PlayerDirection::all().into_iter().map(|dir| Node { coord: c.clone(), dir }).collect()
+    }
+
+    /// Position of the pose in a vector with the 4 directions of each block together, if it is in the map
+    pub fn index(&self, width: i16, height: i16) -> Option<usize> {
+        let c = &self.coord;
+        if c.x < 0 || c.y < 0 || c.x >= width || c.y >= height { return None }
+        let dir = match self.dir {
+            PlayerDirection::NORTH => 0,
+            PlayerDirection::EAST => 1,
+            PlayerDirection::SOUTH => 2,
+            PlayerDirection::WEST => 3,
+        };
+        Some((c.x as usize * height as usize + c.y as usize) * 4 + dir)
+    }
+
+    /// Pose in the position `i` of such a vector
+    pub fn from_index(i: usize, height: i16) -> Node {
+        let block = i / 4;
+        let coord = Coord { x: (block / height as usize) as i16, y: (block % height as usize) as i16 };
+        Node { coord, dir: PlayerDirection::all()[i % 4].clone() }
+    }
+
+    /// Never more than the cost of the cheapest path to `goal`, walking or through a known teleport
+    pub fn distance_to_goal(&self, f: &Field, goal: &Coord) -> OrderedFloat<f64> {
+        let step = min_step(f);
+        let walk = step * self.coord.manhattan(goal) as f64;
+        let jump = f.teleports.values().flatten()
+            .map(|exit| TELEPORT_COST + step * exit.manhattan(goal) as f64)
+            .fold(walk, f64::min);
+        OrderedFloat(jump)
     }
 
     pub fn neighbours(&self, f:&Field) -> Vec<(Node, OrderedFloat<f64>)> {
+        let p = &f.config.params;
         let find_cost = |c: &Coord, is_back: bool| -> OrderedFloat<f64> {
             let mut cost = 1.0;
-            if is_back { cost += 1.5 }
-            if query::is_safe(f, &c) { cost *= 0.8 }
-            if query::is_unsafe(f, &c) { cost += 10.0 }
+            if is_back { cost += p.back_penalty }
+            if query::is_safe(f, c) { cost *= p.safe_bonus }
+            cost += p.unsafe_penalty * query::danger(f, c);
+            let h = query::hazard(f, c);
+            cost += h.hole * HOLE_COST + h.teleport * TELEPORT_RISK_COST;
             OrderedFloat(cost)
         };
 
@@ -39,10 +83,27 @@ impl Node {
         ];
         ret.retain(|(n, _)| {
             match query::get(f, &n.coord) {
-                Position::DANGER | Position::WALL | Position::UNKNOWN => false,
+                Position::WALL | Position::UNKNOWN | Position::HOLE | Position::TELEPORT => false,
+                Position::SUSPECTED_HOLE | Position::SUSPECTED_TELEPORT => {
+                    let h = query::hazard(f, &n.coord);
+                    h.hole < MAX_HOLE_PROBABILITY && h.teleport < MAX_TELEPORT_PROBABILITY
+                },
                 _ => true
             }
         });
+
+        // walking into a teleport is worth it only if it is known where it goes
+        if query::get(f, &front) == Position::TELEPORT {
+            if let Some(exit) = query::teleport_exit(f, &front) {
+                ret.push((Node { coord: exit, dir: self.dir.clone() }, OrderedFloat(TELEPORT_COST)));
+            }
+        }
         ret
     }
 }
+
+/// Least cost of a step to another block, as safe blocks and walking back may cost less than 1
+fn min_step(f: &Field) -> f64 {
+    let p = &f.config.params;
+    (p.safe_bonus.min(1.0) * (1.0 + p.back_penalty.min(0.0))).max(0.0)
+}
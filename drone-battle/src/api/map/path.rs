@@ -1,4 +1,4 @@
-use crate::api::enums::{Action};
+use crate::api::enums::{Action, PlayerDirection};
 use crate::api::map::Coord;
 use crate::api::map::node::Node;
 
@@ -9,6 +9,8 @@ pub struct Path {
     pub actions: Vec<Action>,
     pub size: usize,
     pub dest: Coord,
+    /// Direction of the bot after the last action
+    pub dest_dir: PlayerDirection,
     pub coords: Vec<Coord>,
 }
 
@@ -56,6 +58,9 @@ impl Path {
             else if p.dir.right() == node.dir {      // right?
                 v.push(Action::RIGHT)
             }
+            else if p.coord != node.coord {         // walked into a teleport
+                v.push(Action::FRONT)
+            }
 
             previous = Some(node);        // update previous
         }
@@ -65,6 +70,7 @@ impl Path {
             actions: v,
             size,
             dest: nodes.last()?.coord.clone(),
+            dest_dir: nodes.last()?.dir.clone(),
             coords: v_coords
         })
     }
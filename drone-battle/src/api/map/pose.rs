@@ -0,0 +1,98 @@
+use crate::api::enums::{Action, PlayerDirection};
+use crate::api::map::Coord;
+
+/// What happened to the pose of the bot since the last tick, compared with what the last action should do
+#[derive(Clone, Debug, PartialEq)]
+#[allow(non_camel_case_types)]
+pub enum Motion {
+    // the bot is where the action should take it
+    EXPECTED,
+    // the bot walked into something, and stayed in place
+    BLOCKED,
+    // the bot walked into the block and ended up farther than any move explains
+    TELEPORT { entry: Coord },
+    // nothing changed, as if the server missed the action
+    IGNORED,
+    // the bot died and came back
+    RESPAWN,
+    // the server put the bot in another pose, as a late move or a correction
+    CORRECTION,
+}
+
+/// Where the action takes the bot, if nothing is in the way
+pub fn predict(c: &Coord, dir: &PlayerDirection, action: &Action) -> (Coord, PlayerDirection) {
+    match action {
+        Action::FRONT => (c.next(dir), dir.clone()),
+        Action::BACK => (c.next(&dir.opposite()), dir.clone()),
+        Action::LEFT => (c.clone(), dir.left()),
+        Action::RIGHT => (c.clone(), dir.right()),
+        _ => (c.clone(), dir.clone()),
+    }
+}
+
+/// Compares the pose predicted from the last pose and action with the pose told by the server
+pub fn classify(
+    last: &Coord, last_dir: &PlayerDirection, action: &Action,
+    c: &Coord, dir: &PlayerDirection, blocked: bool, respawned: bool
+) -> Motion {
+    if respawned { return Motion::RESPAWN }
+
+    let (expected, expected_dir) = predict(last, last_dir, action);
+    let walking = matches!(action, Action::FRONT | Action::BACK);
+
+    if *c == expected && *dir == expected_dir { Motion::EXPECTED }
+    else if walking && blocked && c == last && dir == last_dir { Motion::BLOCKED }
+    else if c == last && dir == last_dir { Motion::IGNORED }
+    // a move the server applied late, or twice, leaves the bot next to where it was or should be
+    else if walking && c.manhattan(&expected) > 1 && c.manhattan(last) > 1 { Motion::TELEPORT { entry: expected } }
+    else { Motion::CORRECTION }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LAST: Coord = Coord { x: 5, y: 5 };
+
+    fn motion(action: Action, x: i16, y: i16, dir: PlayerDirection, blocked: bool) -> Motion {
+        classify(&LAST, &PlayerDirection::NORTH, &action, &Coord { x, y }, &dir, blocked, false)
+    }
+
+    #[test]
+    fn expected_moves() {
+        assert_eq!(motion(Action::FRONT, 5, 4, PlayerDirection::NORTH, false), Motion::EXPECTED);
+        assert_eq!(motion(Action::BACK, 5, 6, PlayerDirection::NORTH, false), Motion::EXPECTED);
+        assert_eq!(motion(Action::LEFT, 5, 5, PlayerDirection::WEST, false), Motion::EXPECTED);
+        assert_eq!(motion(Action::NOTHING, 5, 5, PlayerDirection::NORTH, false), Motion::EXPECTED);
+    }
+
+    #[test]
+    fn blocked_and_ignored() {
+        assert_eq!(motion(Action::FRONT, 5, 5, PlayerDirection::NORTH, true), Motion::BLOCKED);
+        assert_eq!(motion(Action::FRONT, 5, 5, PlayerDirection::NORTH, false), Motion::IGNORED);
+        assert_eq!(motion(Action::RIGHT, 5, 5, PlayerDirection::NORTH, false), Motion::IGNORED);
+    }
+
+    #[test]
+    fn respawn_wins() {
+        let m = classify(&LAST, &PlayerDirection::NORTH, &Action::FRONT, &Coord { x: 1, y: 1 }, &PlayerDirection::SOUTH, false, true);
+        assert_eq!(m, Motion::RESPAWN);
+    }
+
+    #[test]
+    fn far_jump_is_a_teleport() {
+        assert_eq!(motion(Action::FRONT, 20, 9, PlayerDirection::NORTH, false), Motion::TELEPORT { entry: Coord { x: 5, y: 4 } });
+        assert_eq!(motion(Action::BACK, 1, 1, PlayerDirection::NORTH, false), Motion::TELEPORT { entry: Coord { x: 5, y: 6 } });
+    }
+
+    #[test]
+    fn late_or_small_moves_are_corrections() {
+        // the last move was applied twice
+        assert_eq!(motion(Action::FRONT, 5, 3, PlayerDirection::NORTH, false), Motion::CORRECTION);
+        // moved to the side, or back instead of forward
+        assert_eq!(motion(Action::FRONT, 6, 4, PlayerDirection::NORTH, false), Motion::CORRECTION);
+        assert_eq!(motion(Action::FRONT, 5, 6, PlayerDirection::NORTH, false), Motion::CORRECTION);
+        // a turn never teleports
+        assert_eq!(motion(Action::LEFT, 9, 9, PlayerDirection::WEST, false), Motion::CORRECTION);
+    }
+}
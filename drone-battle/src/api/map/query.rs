@@ -2,27 +2,121 @@ use std::collections::HashMap;
 use std::ops::Mul;
 use std::time::Duration;
 use crate::api::enums::PlayerDirection;
-use crate::api::map::{Position, Field, Coord, MAP_HEIGHT, MAP_WIDTH};
+use crate::api::map::{Position, Field, Coord};
 use crate::api::map::path::Path;
-use crate::api::map::logic;
+use crate::api::map::distance;
+use crate::api::map::inference::{self, HazardBelief};
+use crate::api::map::enemies;
 
 pub fn get(f: &Field, c: &Coord) -> Position {
-    if c.x < 0 || c.y < 0 || c.x >= MAP_WIDTH || c.y >= MAP_HEIGHT { Position::WALL }
-    else { f.map.get(c).unwrap_or(&Position::UNKNOWN).clone() }
+    f.grid.get(c)
 }
 
-pub fn is_unsafe(f: &Field, c: &Coord) -> bool { f.unsafe_positions.contains_key(c) }
+/// Percentage of the map already known, hazards suspected by the inference not included
+pub fn coverage(f: &Field) -> f64 {
+    let known = f.grid.known().filter(|(_, p)| !p.is_suspected()).count();
+    100.0 * known as f64 / (f.width() as f64 * f.height() as f64).max(1.0)
+}
 
-pub fn is_safe(f: &Field, c: &Coord) -> bool { f.safe_positions.contains_key(c) }
+pub fn is_unsafe(f: &Field, c: &Coord) -> bool { f.grid.is_unsafe(c) }
 
-pub fn has_gold(f: &Field) -> bool { !f.gold_positions.is_empty() }
+/// How dangerous a block in a firing lane is, from 0 to 1
+pub fn danger(f: &Field, c: &Coord) -> f64 { f.grid.danger(c) }
 
-pub fn has_powerup(f: &Field) -> bool { !f.powerup_positions.is_empty() }
+pub fn is_safe(f: &Field, c: &Coord) -> bool { f.grid.is_safe(c) }
+
+/// Probability of a block having a hole or a teleport, according to the inference
+pub fn hazard(f: &Field, c: &Coord) -> HazardBelief {
+    match get(f, c) {
+        Position::HOLE => HazardBelief { hole: 1.0, teleport: 0.0 },
+        Position::TELEPORT => HazardBelief { hole: 0.0, teleport: 1.0 },
+        Position::UNKNOWN | Position::SUSPECTED_HOLE | Position::SUSPECTED_TELEPORT => {
+            f.inference.belief(c).cloned().unwrap_or(
+                HazardBelief { hole: inference::HAZARD_PRIOR, teleport: inference::HAZARD_PRIOR }
+            )
+        },
+        _ => HazardBelief::default()
+    }
+}
 
-pub fn has_gold_to_collect(f: &Field, c: &Coord, dir: &PlayerDirection) -> Option<Path> {
-    has_something_to_collect(f, c, dir, &f.gold_positions)
+/// Probability of an enemy being in the block
+pub fn enemy(f: &Field, c: &Coord) -> f64 { f.enemies.get(c) }
+
+/// Probability of some enemy seeing the block, from a block in line with it and facing it.
+///
+/// The direction of each enemy is not known, so it faces the block one time in four
+pub fn enemy_sees(f: &Field, c: &Coord) -> f64 {
+    let none: f64 = PlayerDirection::all().iter()
+        .flat_map(|d| enemies::line(f, c, d))
+        .map(|e| 1.0 - enemy(f, &e) / 4.0)
+        .product();
+    1.0 - none
+}
+
+/// Probability of some enemy being in the line of fire, facing `dir` from `c`
+pub fn enemy_in_line(f: &Field, c: &Coord, dir: &PlayerDirection) -> f64 {
+    let none: f64 = enemies::line(f, c, dir).iter().map(|e| 1.0 - enemy(f, e)).product();
+    1.0 - none
 }
 
+/// Direction to face to have the most chance of an enemy in the line of fire, and that chance
+pub fn best_facing(f: &Field, c: &Coord) -> (PlayerDirection, f64) {
+    PlayerDirection::all().into_iter()
+        .map(|d| { let p = enemy_in_line(f, c, &d); (d, p) })
+        .fold((PlayerDirection::NORTH, -1.0), |best, (d, p)| if p > best.1 { (d, p) } else { best })
+}
+
+/// Blocks seen from `c` facing `dir`, up to `range` blocks or the first wall.
+///
+/// Blocks not known yet are taken as open: hiding behind a wall that may not be there is not safe
+pub fn visible(f: &Field, c: &Coord, dir: &PlayerDirection, range: i16) -> Vec<Coord> {
+    let mut ret: Vec<Coord> = Vec::new();
+    let mut next = c.next(dir);
+    while ret.len() < range.max(0) as usize && get(f, &next) != Position::WALL {
+        ret.push(next.clone());
+        next = next.next(dir);
+    }
+    ret
+}
+
+/// If a player at `a` can see (and shoot) one at `b`: in line, up to the view range, and no wall between them
+pub fn can_see(f: &Field, a: &Coord, b: &Coord) -> bool {
+    let dir = if a == b { return true }
+        else if a.x == b.x { if b.y < a.y { PlayerDirection::NORTH } else { PlayerDirection::SOUTH } }
+        else if a.y == b.y { if b.x < a.x { PlayerDirection::WEST } else { PlayerDirection::EAST } }
+        else { return false };
+    let distance = a.manhattan(b);
+    distance <= enemies::VIEW_RANGE as u16 && visible(f, a, &dir, distance as i16).len() == distance as usize
+}
+
+/// Blocks up to `radius` steps from `c` where the bot can stand, and none of the `threats` can see
+pub fn cover(f: &Field, c: &Coord, radius: u8, threats: &[Coord]) -> Vec<Coord> {
+    let r = radius as i16;
+    let mut ret: Vec<Coord> = Vec::new();
+    for x in (c.x - r)..=(c.x + r) {
+        for y in (c.y - r)..=(c.y + r) {
+            let b = Coord { x, y };
+            if b.manhattan(c) > radius as u16 { continue }
+            let p = get(f, &b);
+            if p == Position::WALL || p.is_hazard() { continue }
+            if threats.iter().any(|t| can_see(f, t, &b)) { continue }
+            ret.push(b);
+        }
+    }
+    ret
+}
+
+/// Where the teleport sends the bot to, if it always sent it to the same place
+pub fn teleport_exit(f: &Field, c: &Coord) -> Option<Coord> {
+    let exits = f.teleports.get(c)?;
+    let first = exits.first()?;
+    if exits.iter().all(|e| e == first) { Some(first.clone()) } else { None }
+}
+
+pub fn has_gold(f: &Field) -> bool { !f.gold_positions.is_empty() }
+
+pub fn has_powerup(f: &Field) -> bool { !f.powerup_positions.is_empty() }
+
 pub fn has_powerup_to_collect(f: &Field, c: &Coord, dir: &PlayerDirection) -> Option<Path> {
     has_something_to_collect(f, c, dir, &f.powerup_positions)
 }
@@ -33,9 +127,10 @@ fn has_something_to_collect(
     // let mut time_to_born: Duration;
     let mut best_path: Option<Path> = None;
     let mut current_path: Option<Path>;
+    let distances = distance::from(f, current_coord, dir);
 
     for (something_coord, time) in hm.iter() {
-        current_path = logic::a_star(f,current_coord, dir, &something_coord);
+        current_path = distances.path(something_coord);
         match &current_path {
             None => continue,           // no path to place
             Some(cp) => {
@@ -0,0 +1,162 @@
+use crate::api::enums::PlayerDirection;
+use crate::api::map::{Coord, Field};
+use crate::api::map::logic;
+use crate::api::map::distance::{self, DistanceField};
+use crate::api::map::path::Path;
+
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::time::Duration;
+
+/// Value of collecting a gold. Powerup values are given in the same unit
+pub const GOLD_VALUE: f64 = 100.0;
+
+/// Maximum number of stops in a route
+const MAX_STOPS: usize = 4;
+/// Only the closest targets are used when planning a route
+const MAX_CANDIDATES: usize = 6;
+/// Longest time the bot accepts to wait at a stop for something to spawn
+const MAX_WAIT: Duration = Duration::from_secs(3);
+
+/// Something that can be collected
+struct Target {
+    coord: Coord,
+    timer: Duration,
+    value: f64,
+}
+
+/// A sequence of paths, collecting something at the end of each one
+#[derive(Clone, Debug)]
+pub struct Route {
+    /// Path to each stop, each one starting at the end of the previous one
+    pub stops: Vec<Path>,
+    /// Expected score per second of the whole route
+    pub score_rate: f64,
+    /// Which targets were ready when the route was planned
+    key: Vec<(Coord, bool)>,
+}
+
+impl Route {
+    pub fn first(&self) -> Option<&Path> { self.stops.first() }
+
+    /// Checks if something spawned, was collected or was found since the route was planned
+    pub fn is_outdated(&self, f: &Field) -> bool { self.key != route_key(f) }
+}
+
+fn route_key(f: &Field) -> Vec<(Coord, bool)> {
+    let mut key: Vec<(Coord, bool)> = golds(f)
+        .chain(f.powerup_positions.iter())
+        .map(|(c, t)| (c.clone(), *t >= f.config.spawn_timer))
+        .collect();
+    key.sort();
+    key
+}
+
+/// Golds that no teammate is going after
+fn golds(f: &Field) -> impl Iterator<Item = (&Coord, &Duration)> {
+    f.gold_positions.iter().filter(|(c, _)| !f.reserved.contains_key(c))
+}
+
+/// Route through the known golds, and powerups if `powerup_value` is given, with the best score per second
+pub fn plan(f: &Field, c: &Coord, dir: &PlayerDirection, powerup_value: Option<f64>) -> Option<Route> {
+    let targets = targets(f, powerup_value);
+    let distances = distance::from(f, c, dir);
+    let mut planner = Planner { f, distances, targets, legs: HashMap::new(), visited: vec![], stops: vec![], best: None };
+
+    // only the closest targets are worth a route
+    let mut first: Vec<(usize, usize)> = (0..planner.targets.len())
+        .filter_map(|i| Some((i, planner.leg(c, dir, i)?.size)))
+        .collect();
+    first.sort_by_key(|(_, size)| *size);
+    let candidates: Vec<usize> = first.iter().take(MAX_CANDIDATES).map(|(i, _)| *i).collect();
+
+    planner.search(c.clone(), dir.clone(), Duration::from_secs(0), 0.0, &candidates);
+
+    let (stops, score_rate) = planner.best?;
+    Some(Route { stops, score_rate, key: route_key(f) })
+}
+
+/// Path to the closest target a route could start with, spawned by the time the bot arrives or soon after
+pub fn closest(f: &Field, c: &Coord, dir: &PlayerDirection, powerup_value: Option<f64>) -> Option<Path> {
+    let distances = distance::from(f, c, dir);
+    targets(f, powerup_value).iter()
+        .filter_map(|t| {
+            let path = distances.path(&t.coord)?;
+            let arrival = f.config.normal_timer * path.size as u32;
+            let wait = f.config.spawn_timer.saturating_sub(t.timer + arrival);
+            if wait > MAX_WAIT { None } else { Some(path) }
+        })
+        .min_by_key(|p| p.size)
+}
+
+fn targets(f: &Field, powerup_value: Option<f64>) -> Vec<Target> {
+    let mut targets: Vec<Target> = golds(f)
+        .map(|(c, t)| Target { coord: c.clone(), timer: *t, value: GOLD_VALUE })
+        .collect();
+    if let Some(v) = powerup_value {
+        targets.extend(f.powerup_positions.iter().map(|(c, t)| Target { coord: c.clone(), timer: *t, value: v }));
+    }
+    targets
+}
+
+struct Planner<'a> {
+    f: &'a Field,
+    /// Costs from the bot
+    distances: Rc<DistanceField>,
+    targets: Vec<Target>,
+    /// Cached paths from some pose to some target
+    legs: HashMap<(Coord, PlayerDirection, usize), Option<Path>>,
+    /// Current route being tried
+    visited: Vec<usize>,
+    stops: Vec<Path>,
+    best: Option<(Vec<Path>, f64)>,
+}
+
+impl Planner<'_> {
+    fn leg(&mut self, c: &Coord, dir: &PlayerDirection, target: usize) -> Option<Path> {
+        let f = self.f;
+        let dest = &self.targets[target].coord;
+        self.legs.entry((c.clone(), dir.clone(), target))
+            .or_insert_with(|| {
+                // the legs from the bot come from the search of the tick, the others are searched one by one
+                let from_bot = self.distances.origin().coord == *c && self.distances.origin().dir == *dir;
+                if from_bot { self.distances.path(dest) } else { logic::a_star(f, c, dir, dest) }
+            })
+            .clone()
+    }
+
+    /// Tries every order of the candidates, keeping the best score per second
+    fn search(&mut self, c: Coord, dir: PlayerDirection, elapsed: Duration, value: f64, candidates: &[usize]) {
+        let tick = self.f.config.normal_timer;
+        let spawn = self.f.config.spawn_timer;
+
+        for &i in candidates {
+            if self.visited.contains(&i) { continue }
+            let leg = match self.leg(&c, &dir, i) {
+                Some(p) => p,
+                None => continue
+            };
+
+            // waiting for it to spawn, if needed
+            let arrival = elapsed + tick * leg.size as u32;
+            let wait = spawn.saturating_sub(self.targets[i].timer + arrival);
+            if wait > MAX_WAIT { continue }
+
+            let done = arrival + wait + tick;       // one more tick to get it
+            let total = value + self.targets[i].value;
+            let rate = total / done.as_secs_f64();
+
+            let (next_c, next_dir) = (leg.dest.clone(), leg.dest_dir.clone());
+            self.visited.push(i);
+            self.stops.push(leg);
+            if self.best.as_ref().is_none_or(|(_, r)| rate > *r) {
+                self.best = Some((self.stops.clone(), rate));
+            }
+            if self.stops.len() < MAX_STOPS {
+                self.search(next_c, next_dir, done, total, candidates);
+            }
+            self.stops.pop();
+            self.visited.pop();
+        }
+    }
+}
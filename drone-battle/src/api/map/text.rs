@@ -0,0 +1,317 @@
+use crate::api::config::Config;
+use crate::api::enums::PlayerDirection;
+use crate::api::map::{Coord, Field, Position, MAX_INFERRED_SIZE};
+use crate::api::map::{query, update};
+use crate::api::map::node::Node;
+use crate::api::map::path::Path;
+
+use std::time::Duration;
+
+/// Block of the path of the bot
+const PATH: char = '*';
+/// Block in a firing lane
+const UNSAFE: char = '!';
+
+/// A field read from text, with what was drawn over it
+pub struct TextMap {
+    pub field: Field,
+    /// Pose of the bot, from its arrow or from its line
+    pub bot: Option<Node>,
+    /// Blocks drawn as the path, from the top left
+    pub path: Vec<Coord>,
+}
+
+/// Draws the field as text, followed by the lines for what the characters cannot tell, as in
+///
+/// ```text
+/// #######
+/// #.>**$#
+/// #?.!.:#
+/// #######
+/// bot 2 1 EAST
+/// gold 5 1 1500
+/// unsafe 3 2 0.75
+/// ```
+pub fn render(f: &Field, bot: Option<&Node>, path: Option<&Path>) -> String {
+    let mut rows: Vec<Vec<char>> = (0..f.height())
+        .map(|y| (0..f.width()).map(|x| query::get(f, &Coord { x, y }).to_char()).collect())
+        .collect();
+    let coords: Vec<Coord> = (0..f.width()).flat_map(|x| (0..f.height()).map(move |y| Coord { x, y })).collect();
+    let unsafe_blocks: Vec<&Coord> = coords.iter().filter(|c| query::is_unsafe(f, c)).collect();
+
+    let mut draw = |c: &Coord, ch: char| {
+        if query::get(f, c) == Position::EMPTY { rows[c.y as usize][c.x as usize] = ch }
+    };
+    for c in unsafe_blocks.iter() { draw(c, UNSAFE) }
+    if let Some(p) = path {
+        for c in p.coords.iter() { draw(c, PATH) }
+    }
+    if let Some(b) = bot { draw(&b.coord, arrow(&b.dir)) }
+
+    let mut lines: Vec<String> = rows.into_iter().map(|r| r.into_iter().collect()).collect();
+    if let Some(b) = bot { lines.push(format!("bot {} {} {}", b.coord.x, b.coord.y, b.dir.to_string())) }
+    for (kind, timers) in [("gold", &f.gold_positions), ("powerup", &f.powerup_positions)] {
+        let mut timers: Vec<(&Coord, &Duration)> = timers.iter().collect();
+        timers.sort();
+        lines.extend(timers.iter().map(|(c, t)| format!("{} {} {} {}", kind, c.x, c.y, t.as_millis())));
+    }
+    lines.extend(unsafe_blocks.iter().map(|c| format!("unsafe {} {} {}", c.x, c.y, query::danger(f, c) as f32)));
+    let mut teleports: Vec<(&Coord, &Vec<Coord>)> = f.teleports.iter().collect();
+    teleports.sort();
+    for (entry, exits) in teleports {
+        lines.extend(exits.iter().map(|e| format!("teleport {} {} {} {}", entry.x, entry.y, e.x, e.y)));
+    }
+
+    lines.join("\n") + "\n"
+}
+
+/// Reads a field drawn by `render`, with the size of the drawing as the size of the map
+pub fn parse(s: &str, config: &Config) -> Result<TextMap, String> {
+    let lines: Vec<&str> = s.lines().map(|l| l.trim_end()).collect();
+    let first = lines.iter().position(|l| !l.is_empty()).unwrap_or(lines.len());
+    let height = lines[first..].iter().take_while(|l| !l.is_empty() && !l.contains(' ')).count();
+    let rows = &lines[first..first + height];
+    let width = rows.first().map_or(0, |r| r.chars().count());
+    if let Some(y) = rows.iter().position(|r| r.chars().count() != width) {
+        return Err(format!("line {}: expected {} blocks, as in the first row, got {}", first + y + 1, width, rows[y].chars().count()))
+    }
+    if width > MAX_INFERRED_SIZE as usize || height > MAX_INFERRED_SIZE as usize {
+        return Err(format!("line {}: the map is bigger than {} blocks", first + 1, MAX_INFERRED_SIZE))
+    }
+
+    let mut config = config.clone();
+    config.map_width = width as i16;
+    config.map_height = height as i16;
+    let mut ret = TextMap { field: Field::new(&config), bot: None, path: Vec::new() };
+
+    for (y, row) in rows.iter().enumerate() {
+        for (x, ch) in row.chars().enumerate() {
+            let c = Coord { x: x as i16, y: y as i16 };
+            let p = match ch {
+                PATH => { ret.path.push(c.clone()); Position::EMPTY },
+                UNSAFE => { ret.field.grid.set_danger(&c, 1.0); Position::EMPTY },
+                _ => match PlayerDirection::all().into_iter().find(|d| arrow(d) == ch) {
+                    Some(dir) => { ret.bot = Some(Node { coord: c.clone(), dir }); Position::EMPTY },
+                    None => Position::from_char(ch)
+                        .ok_or_else(|| format!("line {}: unknown block '{}'", first + y + 1, ch))?,
+                }
+            };
+            if p == Position::SAFE { update::set_safe(&mut ret.field, c.clone()) }
+            update::set(&mut ret.field, c, p, true);
+        }
+    }
+
+    for (i, line) in lines.iter().enumerate().skip(first + height) {
+        if line.is_empty() { continue }
+        read_line(&mut ret, line).ok_or_else(|| format!("line {}: could not read '{}'", i + 1, line))?;
+    }
+    Ok(ret)
+}
+
+/// One of the lines after the map, as `gold 5 1 1500`
+fn read_line(m: &mut TextMap, line: &str) -> Option<()> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let number = |i: usize| -> Option<f64> { words.get(i)?.parse().ok() };
+    let coord = |i: usize| -> Option<Coord> {
+        Some(Coord { x: words.get(i)?.parse().ok()?, y: words.get(i + 1)?.parse().ok()? })
+    };
+    let f = &mut m.field;
+
+    match words[0] {
+        "bot" => {
+            let dir = PlayerDirection::all().into_iter().find(|d| words.get(3).is_some_and(|w| *w == d.to_string()))?;
+            m.bot = Some(Node { coord: coord(1)?, dir });
+        },
+        "gold" => { f.gold_positions.insert(coord(1)?, Duration::from_millis(number(3)? as u64)); },
+        "powerup" => { f.powerup_positions.insert(coord(1)?, Duration::from_millis(number(3)? as u64)); },
+        "unsafe" => f.grid.set_danger(&coord(1)?, number(3)?),
+        "teleport" => f.teleports.entry(coord(1)?).or_default().push(coord(3)?),
+        _ => return None
+    }
+    Some(())
+}
+
+fn arrow(dir: &PlayerDirection) -> char {
+    match dir {
+        PlayerDirection::NORTH => '^',
+        PlayerDirection::EAST => '>',
+        PlayerDirection::SOUTH => 'v',
+        PlayerDirection::WEST => '<',
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::map::{distance, inference, logic};
+
+    /// Everything the lines after the map can tell
+    const FULL: &str = "\
+#######
+#..#.$#
+#.v#T+#
+#.!...#
+#######
+bot 2 2 SOUTH
+gold 5 1 1500
+powerup 5 2 250
+unsafe 2 3 0.3
+teleport 4 2 1 3
+";
+
+    /// A wall between the bot and the gold, that the path has to go around below
+    const WALL: &str = "\
+#######
+#>.#.$#
+#..#..#
+#.....#
+#######
+";
+
+    /// Blocks of each kind, for the rules of `update::set`
+    const BLOCKS: &str = "\
+#######
+#:.o?.#
+#.Ot.T#
+#$....#
+#######
+";
+
+    /// Two corridors, with a block not known yet and a suspected hole on the way
+    const SIGHT: &str = "\
+#########
+#...?...#
+#.#####.#
+#.o.#...#
+#########
+";
+
+    fn field(s: &str) -> TextMap { parse(s, &Config::default()).expect("a valid map") }
+
+    fn error(s: &str) -> String { parse(s, &Config::default()).err().expect("an invalid map") }
+
+    #[test]
+    fn round_trip() {
+        let m = field(FULL);
+        let text = render(&m.field, m.bot.as_ref(), None);
+        let back = field(&text);
+        assert_eq!(render(&back.field, back.bot.as_ref(), None), text);
+
+        let f = &back.field;
+        assert_eq!((f.width(), f.height()), (7, 5));
+        assert_eq!(back.bot, Some(Node { coord: Coord { x: 2, y: 2 }, dir: PlayerDirection::SOUTH }));
+        assert_eq!(f.gold_positions.get(&Coord { x: 5, y: 1 }), Some(&Duration::from_millis(1500)));
+        assert_eq!(f.powerup_positions.get(&Coord { x: 5, y: 2 }), Some(&Duration::from_millis(250)));
+
+        let lane = Coord { x: 2, y: 3 };
+        assert!(query::is_unsafe(f, &lane));
+        assert!((query::danger(f, &lane) - 0.3).abs() < 1e-6);
+        assert_eq!(query::get(f, &lane), Position::EMPTY);
+
+        assert_eq!(query::get(f, &Coord { x: 4, y: 2 }), Position::TELEPORT);
+        assert_eq!(f.teleports.get(&Coord { x: 4, y: 2 }), Some(&vec![Coord { x: 1, y: 3 }]));
+        assert_eq!(query::get(f, &Coord { x: 3, y: 1 }), Position::WALL);
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(error("###\n##\n").starts_with("line 2:"));
+        assert!(error("###\n#x#\n").starts_with("line 2:"));
+        assert!(error("###\n#.#\nbot 1 1 UP\n").starts_with("line 3:"));
+    }
+
+    #[test]
+    fn path_around_a_wall() {
+        let m = field(WALL);
+        let f = &m.field;
+        let bot = m.bot.expect("drawn by its arrow");
+        let gold = Coord { x: 5, y: 1 };
+
+        let path = logic::a_star(f, &bot.coord, &bot.dir, &gold).expect("a way around the wall");
+        assert_eq!(path.dest, gold);
+        assert!(path.coords.iter().all(|c| query::get(f, c) != Position::WALL));
+        assert!(path.coords.contains(&Coord { x: 3, y: 3 }), "the only way is below the wall");
+
+        let from_bot = distance::from(f, &bot.coord, &bot.dir);
+        assert_eq!(from_bot.path(&gold).map(|p| p.size), Some(path.size));
+
+        // the path drawn over the floor is read back, without the bot and the gold
+        let drawn = field(&render(f, Some(&bot), Some(&path)));
+        let floor: Vec<&Coord> = path.coords.iter().filter(|c| **c != bot.coord && **c != gold).collect();
+        assert_eq!(drawn.path.len(), floor.len());
+        assert!(floor.iter().all(|c| drawn.path.contains(c)));
+    }
+
+    #[test]
+    fn set_keeps_what_is_known() {
+        let mut f = field(BLOCKS).field;
+        let at = |x, y| Coord { x, y };
+
+        // hazards are only drawn over blocks not known, or suspected
+        update::set(&mut f, at(2, 1), Position::HOLE, false);
+        assert_eq!(query::get(&f, &at(2, 1)), Position::EMPTY);
+        update::set(&mut f, at(3, 1), Position::HOLE, false);
+        assert_eq!(query::get(&f, &at(3, 1)), Position::HOLE);
+        update::set(&mut f, at(3, 2), Position::SUSPECTED_HOLE, false);
+        assert_eq!(query::get(&f, &at(3, 2)), Position::SUSPECTED_HOLE);
+
+        update::set(&mut f, at(4, 1), Position::SAFE, false);
+        assert_eq!(query::get(&f, &at(4, 1)), Position::SAFE);
+        assert!(query::is_safe(&f, &at(4, 1)));
+
+        // the floor does not hide a gold, until it is forced
+        update::set(&mut f, at(1, 3), Position::EMPTY, false);
+        assert_eq!(query::get(&f, &at(1, 3)), Position::GOLD);
+        update::set(&mut f, at(1, 3), Position::EMPTY, true);
+        assert_eq!(query::get(&f, &at(1, 3)), Position::EMPTY);
+
+        update::set(&mut f, at(1, 1), Position::WALL, false);
+        assert_eq!(query::get(&f, &at(1, 1)), Position::WALL);
+        assert!(!query::is_safe(&f, &at(1, 1)));
+    }
+
+    #[test]
+    fn hazards() {
+        let f = field(BLOCKS).field;
+        let at = |x, y| Coord { x, y };
+
+        assert_eq!(query::hazard(&f, &at(2, 2)).hole, 1.0);
+        assert_eq!(query::hazard(&f, &at(5, 2)).teleport, 1.0);
+        for c in [at(3, 1), at(3, 2), at(4, 1)] {
+            let h = query::hazard(&f, &c);
+            assert_eq!((h.hole, h.teleport), (inference::HAZARD_PRIOR, inference::HAZARD_PRIOR), "{:?}", c);
+        }
+        let floor = query::hazard(&f, &at(2, 1));
+        assert_eq!((floor.hole, floor.teleport), (0.0, 0.0));
+        assert!(query::get(&f, &at(3, 1)).is_hazard() && !query::get(&f, &at(4, 1)).is_hazard());
+    }
+
+    #[test]
+    fn visible_up_to_a_wall() {
+        let f = field(SIGHT).field;
+        let from = Coord { x: 1, y: 1 };
+
+        let east = query::visible(&f, &from, &PlayerDirection::EAST, 10);
+        assert_eq!(east.len(), 6, "the block not known is taken as open");
+        assert_eq!(east.last(), Some(&Coord { x: 7, y: 1 }));
+        assert_eq!(query::visible(&f, &from, &PlayerDirection::EAST, 3).len(), 3);
+        assert_eq!(query::visible(&f, &from, &PlayerDirection::SOUTH, 10).len(), 2);
+        assert!(query::visible(&f, &from, &PlayerDirection::WEST, 10).is_empty());
+    }
+
+    #[test]
+    fn cover_from_threats() {
+        let f = field(SIGHT).field;
+        let c = Coord { x: 3, y: 3 };
+
+        // the suspected hole is not a place to stand, and the first threat sees the rest of its corridor
+        let threats = [Coord { x: 1, y: 1 }];
+        assert_eq!(query::cover(&f, &c, 2, &threats), vec![Coord { x: 3, y: 3 }, Coord { x: 5, y: 3 }]);
+
+        let threats = [Coord { x: 1, y: 1 }, Coord { x: 7, y: 3 }];
+        assert_eq!(query::cover(&f, &c, 2, &threats), vec![Coord { x: 3, y: 3 }]);
+
+        // walls hide from a threat standing in the middle
+        assert_eq!(query::cover(&f, &c, 2, std::slice::from_ref(&c)), vec![Coord { x: 3, y: 1 }, Coord { x: 5, y: 3 }]);
+    }
+}
@@ -0,0 +1,67 @@
+use crate::api::enums::PlayerDirection;
+use crate::api::map::{Coord, Field};
+use crate::api::map::{enemies, query};
+use crate::api::structs::LastObservation;
+
+/// How much of the danger of a block is left after each tick
+const DANGER_DECAY: f64 = 0.75;
+/// Danger under which a block is not unsafe anymore
+const MIN_DANGER: f64 = 0.1;
+/// Least chance of an enemy in a block to hide from it
+const MIN_THREAT_BELIEF: f64 = 0.1;
+
+/// The bot took a shot at `c`: marks the firing lanes it may have come from as unsafe
+pub fn fired(f: &mut Field, c: &Coord, shooter: Option<&Coord>) {
+    // a shooter in line with the bot is the only lane, else each corridor is as dangerous as the belief in it
+    let lanes: Vec<(PlayerDirection, f64)> = match shooter.and_then(|s| facing(f, c, s)) {
+        Some(d) => vec![(d, 1.0)],
+        None => {
+            let belief: Vec<(PlayerDirection, f64)> = PlayerDirection::all().into_iter()
+                .map(|d| { let p = query::enemy_in_line(f, c, &d); (d, p) })
+                .collect();
+            let max = belief.iter().map(|(_, p)| *p).fold(0.0, f64::max);
+            // nothing in the belief, every corridor is as dangerous
+            belief.into_iter().map(|(d, p)| (d, if max > 0.0 { p / max } else { 1.0 })).collect()
+        }
+    };
+
+    for (d, danger) in lanes {
+        if danger < MIN_DANGER { continue }
+        // backing away inside the lane keeps the bot in sight
+        let lane: Vec<Coord> = enemies::line(f, c, &d).into_iter()
+            .chain(std::iter::once(c.clone()))
+            .chain(enemies::line(f, c, &d.opposite()))
+            .collect();
+        for l in lane { mark(f, l, danger) }
+    }
+}
+
+/// Blocks where an enemy that may shoot the bot at `c` probably is
+pub fn sources(f: &Field, c: &Coord, dir: &PlayerDirection, o: &LastObservation) -> Vec<Coord> {
+    let mut ret: Vec<Coord> = Vec::new();
+    if o.is_enemy_front && o.distance_enemy_front > 0 {
+        if let Some(e) = enemies::line(f, c, dir).get(o.distance_enemy_front as usize - 1) { ret.push(e.clone()) }
+    }
+    if let Some(s) = &o.shooter { ret.push(s.clone()) }
+    ret.extend(
+        f.enemies.above(MIN_THREAT_BELIEF).into_iter()
+            .map(|(e, _)| e)
+            .filter(|e| e.manhattan(c) <= enemies::VIEW_RANGE as u16)
+    );
+    ret
+}
+
+/// Direction from `c` to `s`, if they are in line and nothing blocks the view
+fn facing(f: &Field, c: &Coord, s: &Coord) -> Option<PlayerDirection> {
+    PlayerDirection::all().into_iter().find(|d| enemies::line(f, c, d).contains(s))
+}
+
+fn mark(f: &mut Field, c: Coord, danger: f64) {
+    let current = f.grid.danger(&c);
+    f.grid.set_danger(&c, current.max(danger));
+}
+
+/// Lanes get safer as time goes by, as the shooter may have left
+pub fn decay(f: &mut Field) {
+    f.grid.decay(DANGER_DECAY, MIN_DANGER);
+}
@@ -1,5 +1,5 @@
 use crate::api::map::{Field, Position, Coord};
-use crate::api::map::query;
+use crate::api::map::{query, threat};
 
 use std::time::Duration;
 use crate::api::enums::PlayerDirection;
@@ -14,7 +14,7 @@ pub fn set(f: &mut Field, c: Coord, p: Position, force: bool) {
             Position::GOLD => set_gold(f, c.clone()),
             _ => {}
         }
-        f.map.insert(c, p);
+        f.grid.set(&c, p);
         return
     }
 
@@ -22,22 +22,22 @@ pub fn set(f: &mut Field, c: Coord, p: Position, force: bool) {
     if p == current_position { return; }
 
     match p {
-        Position::DANGER => {
-            if current_position == Position::UNKNOWN {
-                f.map.insert(c.clone(), p);
+        Position::HOLE | Position::TELEPORT | Position::SUSPECTED_HOLE | Position::SUSPECTED_TELEPORT => {
+            if current_position == Position::UNKNOWN || current_position.is_suspected() {
+                f.grid.set(&c, p);
             }
             return;
         },
         Position::SAFE => {
-            if current_position == Position::UNKNOWN || current_position == Position::DANGER {
-                f.map.insert(c.clone(), p);
+            if current_position == Position::UNKNOWN || current_position.is_suspected() {
+                f.grid.set(&c, p);
                 set_safe(f, c.clone());
             };
             return;
         },
         Position::EMPTY =>  {
             if current_position != Position::GOLD && current_position != Position::POWERUP {
-                f.map.insert(c.clone(), p);
+                f.grid.set(&c, p);
             }
             return;
         },
@@ -45,7 +45,7 @@ pub fn set(f: &mut Field, c: Coord, p: Position, force: bool) {
     }
 
     remove_safe(f, &c);
-    f.map.insert(c, p);
+    f.grid.set(&c, p);
 }
 
 pub fn set_gold(f: &mut Field, c: Coord) {
@@ -58,20 +58,20 @@ pub fn set_powerup(f: &mut Field, c: Coord) {
     f.powerup_positions.insert(c, Duration::from_secs(0));
 }
 
-pub fn set_safe(f: &mut Field, c: Coord) { f.safe_positions.insert(c, true); }
+/// Marks `entry` as a teleport, that sent the bot to `exit`
+pub fn set_teleport(f: &mut Field, entry: Coord, exit: Coord) {
+    remove_safe(f, &entry);
+    f.teleports.entry(entry.clone()).or_default().push(exit);
+    f.grid.set(&entry, Position::TELEPORT);
+    f.grid.touch(&entry);       // the exit changed, even if the block did not
+}
 
-pub fn set_unsafe(f: &mut Field, c: Coord) { f.unsafe_positions.insert(c, 1); }
+pub fn set_safe(f: &mut Field, c: Coord) { f.grid.set_safe(&c, true); }
 
-pub fn remove_safe(f: &mut Field, c: &Coord) { f.safe_positions.remove(c); }
+pub fn remove_safe(f: &mut Field, c: &Coord) { f.grid.set_safe(c, false); }
 
 pub fn set_custom(f: &mut Field, c: &Coord, set_type: SetType, dir: Option<PlayerDirection>, p: Position) {
     match set_type {
-        SetType::AROUND => {
-            set(f, Coord {x: c.x + 1, y: c.y }, p.clone(), false);
-            set(f, Coord {x: c.x - 1, y: c.y }, p.clone(), false);
-            set(f, Coord {x: c.x, y: c.y + 1 }, p.clone(), false);
-            set(f, Coord {x: c.x, y: c.y - 1 }, p.clone(), false);
-        },
         SetType::FRONT => {
             set(f, c.next(&dir.unwrap_or(PlayerDirection::NORTH)), p, false)
         },
@@ -88,14 +88,11 @@ pub fn do_tick(f: &mut Field, dur: Duration) {
     for pw in f.powerup_positions.values_mut() { *pw += dur; }
 
     // updating all unsafe positions
-    // removes if tick > 7, else += 1
-    f.unsafe_positions.retain(|_, t| *t > 7);
-    for up in f.unsafe_positions.values_mut() { *up += 1 }
+    threat::decay(f);
 
 }
 
 pub enum SetType {
     FRONT,
-    AROUND,
     BACK
 }
\ No newline at end of file
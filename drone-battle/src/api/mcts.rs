@@ -0,0 +1,340 @@
+use crate::api::enums::{Action, PlayerDirection};
+use crate::api::map::{Coord, Field, Position};
+use crate::api::map::query;
+use crate::api::map::inference::{self, Hazard};
+use crate::api::map::route::GOLD_VALUE;
+
+use rand::Rng;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+/// Part of `normal_timer` used by the search, leaving the rest for the other work of the tick
+const TIME_BUDGET_RATIO: f64 = 0.5;
+/// Simulations of each search in the local arena, where time is virtual
+pub const ARENA_SIMULATIONS: u32 = 200;
+/// How far the simulations go
+const HORIZON: Duration = Duration::from_secs(3);
+/// Value of the future, for each tick
+const DISCOUNT: f64 = 0.97;
+/// Exploration constant of UCT, in the same unit as the values
+const EXPLORATION: f64 = 1.0;
+
+/// Values of the simulation, in the unit of `GOLD_VALUE`
+const DEATH_COST: f64 = 500.0;
+const TELEPORT_COST: f64 = 100.0;
+const UNSAFE_COST: f64 = 10.0;
+const EXPLORE_VALUE: f64 = 50.0;
+const HIT_VALUE: f64 = 20.0;
+/// Chance of an unknown block being a wall
+const WALL_PRIOR: f64 = 0.1;
+
+/// Rollouts walk forward with this chance, when the block in front looks safe
+const ROLLOUT_FRONT: f64 = 0.75;
+const ROLLOUT_MAX_HAZARD: f64 = 0.2;
+
+const ACTIONS: [Action; 6] = [Action::FRONT, Action::BACK, Action::LEFT, Action::RIGHT, Action::GET, Action::SHOOT];
+
+/// What the search knows about the bot, besides the field
+pub struct Root {
+    pub coord: Coord,
+    pub dir: PlayerDirection,
+    pub energy: i32,
+    // enemy in front, without a wall between
+    pub can_shoot: bool,
+    // percepts of the block of the bot, more recent than the timers of the field
+    pub gold_here: bool,
+    pub powerup_here: bool,
+    // value of each point of energy recovered by a powerup
+    pub powerup_value: f64,
+}
+
+/// When the search stops
+#[derive(Clone, Debug, PartialEq)]
+#[allow(non_camel_case_types)]
+pub enum Budget {
+    // when part of the tick is over, in live play
+    TIME,
+    // after a number of simulations, so a match in the local arena plays the same on any machine
+    SIMULATIONS(u32),
+}
+
+/// Result of a search
+pub struct Decision {
+    pub action: Action,
+    // expected discounted value of the action
+    pub value: f64,
+    pub simulations: u32,
+}
+
+/// Node of the tree, a sequence of actions: each simulation samples its own hazards (open-loop)
+struct Node {
+    visits: u32,
+    total: f64,
+    children: [Option<usize>; ACTIONS.len()],
+}
+
+/// What a block turned out to be, in one simulation
+#[derive(Clone, PartialEq)]
+enum Sample {
+    FREE,
+    WALL,
+    HOLE,
+    TELEPORT,
+}
+
+struct Simulation<'a> {
+    f: &'a Field,
+    root: &'a Root,
+    coord: Coord,
+    dir: PlayerDirection,
+    energy: i32,
+    ticks: u32,
+    value: f64,
+    done: bool,
+    // blocks sampled so far, so the same block is the same thing during the whole simulation
+    layout: HashMap<Coord, Sample>,
+    collected: Vec<Coord>,
+    seen: Vec<Coord>,
+}
+
+/// Chooses an action with Monte-Carlo tree search over what the bot believes of the field
+pub fn search(f: &Field, root: &Root, budget: &Budget, rng: &mut StdRng) -> Decision {
+    let start = Instant::now();
+    let time = f.config.normal_timer.mul_f64(TIME_BUDGET_RATIO);
+    let horizon = (HORIZON.as_millis() / f.config.normal_timer.as_millis().max(1)).max(1) as u32;
+    let unexplained: Vec<(Hazard, Vec<Coord>)> = Hazard::all().into_iter()
+        .flat_map(|h| inference::unexplained(f, h).into_iter().map(move |cands| (h, cands)))
+        .collect();
+
+    let mut nodes: Vec<Node> = vec![Node::new()];
+    let mut simulations: u32 = 0;
+
+    // at least one simulation for each action, even if out of time
+    let over = |simulations: u32| simulations >= ACTIONS.len() as u32 && match budget {
+        Budget::TIME => start.elapsed() >= time,
+        Budget::SIMULATIONS(n) => simulations >= *n,
+    };
+    while !over(simulations) {
+        let mut sim = Simulation::new(f, root);
+        sim.sample_percepts(&unexplained, rng);
+        let mut path: Vec<usize> = vec![0];
+        let mut current = 0;
+
+        // selection and expansion
+        while !sim.done && sim.ticks < horizon {
+            let a = select(&nodes, current, root);
+            sim.step(&ACTIONS[a], rng);
+            match nodes[current].children[a] {
+                Some(child) => {
+                    current = child;
+                    path.push(child);
+                },
+                None => {
+                    nodes.push(Node::new());
+                    let child = nodes.len() - 1;
+                    nodes[current].children[a] = Some(child);
+                    path.push(child);
+                    break
+                }
+            }
+        }
+
+        // rollout
+        while !sim.done && sim.ticks < horizon {
+            let a = sim.rollout_action(rng);
+            sim.step(&a, rng);
+        }
+
+        if !sim.done { sim.value += sim.estimate() }
+        for n in path { nodes[n].visits += 1; nodes[n].total += sim.value; }
+        simulations += 1;
+    }
+
+    let best = (0..ACTIONS.len())
+        .filter_map(|a| nodes[0].children[a].map(|c| (a, &nodes[c])))
+        .filter(|(a, _)| allowed(&ACTIONS[*a], root))
+        .max_by(|(_, x), (_, y)| x.mean().total_cmp(&y.mean()));
+
+    match best {
+        Some((a, n)) => Decision { action: ACTIONS[a].clone(), value: n.mean(), simulations },
+        None => Decision { action: Action::NOTHING, value: 0.0, simulations },
+    }
+}
+
+/// UCT, trying every action once first
+fn select(nodes: &[Node], current: usize, root: &Root) -> usize {
+    let node = &nodes[current];
+    let ln = (node.visits.max(1) as f64).ln();
+    let mut best = (0, f64::MIN);
+
+    for (a, child) in node.children.iter().enumerate() {
+        // shooting only makes sense in the first tick, where the enemy is known
+        if current != 0 && ACTIONS[a] == Action::SHOOT { continue }
+        if current == 0 && !allowed(&ACTIONS[a], root) { continue }
+
+        let score = match child {
+            None => return a,
+            Some(c) => nodes[*c].mean() + EXPLORATION * (ln / nodes[*c].visits as f64).sqrt(),
+        };
+        if score > best.1 { best = (a, score) }
+    }
+    best.0
+}
+
+fn allowed(a: &Action, root: &Root) -> bool {
+    *a != Action::SHOOT || root.can_shoot
+}
+
+impl Node {
+    fn new() -> Node { Node { visits: 0, total: 0.0, children: [None; ACTIONS.len()] } }
+
+    fn mean(&self) -> f64 { if self.visits == 0 { 0.0 } else { self.total / self.visits as f64 } }
+}
+
+impl<'a> Simulation<'a> {
+    fn new(f: &'a Field, root: &'a Root) -> Simulation<'a> {
+        Simulation {
+            f,
+            root,
+            coord: root.coord.clone(),
+            dir: root.dir.clone(),
+            energy: root.energy,
+            ticks: 0,
+            value: 0.0,
+            done: false,
+            layout: HashMap::new(),
+            collected: Vec::new(),
+            seen: Vec::new(),
+        }
+    }
+
+    fn step(&mut self, action: &Action, rng: &mut StdRng) {
+        let discount = DISCOUNT.powi(self.ticks as i32);
+        self.ticks += 1;
+
+        match action {
+            Action::FRONT | Action::BACK => {
+                let dir = if *action == Action::FRONT { self.dir.clone() } else { self.dir.opposite() };
+                let next = self.coord.next(&dir);
+                match self.sample(&next, rng) {
+                    Sample::WALL => {},
+                    Sample::HOLE => {
+                        self.value -= DEATH_COST * discount;
+                        self.done = true;
+                    },
+                    Sample::TELEPORT => match query::teleport_exit(self.f, &next) {
+                        Some(exit) => self.coord = exit,
+                        None => {
+                            self.value -= TELEPORT_COST * discount;
+                            self.done = true;
+                        }
+                    },
+                    Sample::FREE => {
+                        let unexplored = query::is_safe(self.f, &next) || query::get(self.f, &next) == Position::UNKNOWN;
+                        if unexplored && !self.seen.contains(&next) {
+                            self.value += EXPLORE_VALUE * discount;
+                            self.seen.push(next.clone());
+                        }
+                        self.value -= UNSAFE_COST * query::danger(self.f, &next) * discount;
+                        self.coord = next;
+                    }
+                }
+            },
+            Action::LEFT => self.dir = self.dir.left(),
+            Action::RIGHT => self.dir = self.dir.right(),
+            Action::GET => {
+                if self.collected.contains(&self.coord) { return }
+                let at_root = self.coord == self.root.coord;
+                if (at_root && self.root.gold_here) || self.is_ready(self.f.gold_positions.get(&self.coord)) {
+                    self.value += GOLD_VALUE * discount;
+                    self.collected.push(self.coord.clone());
+                } else if ((at_root && self.root.powerup_here) || self.is_ready(self.f.powerup_positions.get(&self.coord)))
+                    && self.energy < 100 {
+                    let recovered = (100 - self.energy).min(20);
+                    self.value += recovered as f64 * self.root.powerup_value * discount;
+                    self.energy += recovered;
+                    self.collected.push(self.coord.clone());
+                }
+            },
+            Action::SHOOT => { if self.ticks == 1 && self.root.can_shoot { self.value += HIT_VALUE } },
+            Action::NOTHING => {}
+        }
+    }
+
+    /// Value of the best gold or safe block to explore beyond the horizon, discounted by its distance
+    fn estimate(&self) -> f64 {
+        let discount = |c: &Coord| DISCOUNT.powi((self.ticks + self.coord.manhattan(c) as u32) as i32);
+        let golds = self.f.gold_positions.keys()
+            .filter(|c| !self.collected.contains(c))
+            .map(|c| GOLD_VALUE * discount(c));
+        let safe = self.f.grid.safe()
+            .filter(|c| !self.seen.contains(c))
+            .map(|c| EXPLORE_VALUE * discount(&c));
+        golds.chain(safe).fold(0.0, f64::max)
+    }
+
+    /// Walks mostly forward, avoiding walls and likely hazards, and collects whatever it is standing on
+    fn rollout_action(&self, rng: &mut StdRng) -> Action {
+        let here = self.f.gold_positions.get(&self.coord).or(self.f.powerup_positions.get(&self.coord));
+        let at_root = self.coord == self.root.coord && (self.root.gold_here || self.root.powerup_here);
+        if (at_root || self.is_ready(here)) && !self.collected.contains(&self.coord) { return Action::GET }
+
+        // does not walk into what is known to be bad
+        let front = self.coord.next(&self.dir);
+        let h = query::hazard(self.f, &front);
+        let blocked = query::get(self.f, &front) == Position::WALL || h.hole + h.teleport > ROLLOUT_MAX_HAZARD;
+        if !blocked && rng.gen_bool(ROLLOUT_FRONT) { Action::FRONT }
+        else if rng.gen_bool(0.5) { Action::LEFT }
+        else { Action::RIGHT }
+    }
+
+    /// Checks if something with the timer will be there at the current tick of the simulation
+    fn is_ready(&self, timer: Option<&Duration>) -> bool {
+        timer.is_some_and(|t| *t + self.f.config.normal_timer * self.ticks >= self.f.config.spawn_timer)
+    }
+
+    // one hazard between the candidates of each unexplained percept, so the blocks agree with every percept
+    fn sample_percepts(&mut self, unexplained: &[(Hazard, Vec<Coord>)], rng: &mut StdRng) {
+        let mut order: Vec<&(Hazard, Vec<Coord>)> = unexplained.iter().collect();
+        order.shuffle(rng);
+
+        let mut hazards: HashSet<(Hazard, &Coord)> = HashSet::new();
+        for (h, cands) in order {
+            if cands.iter().any(|c| hazards.contains(&(*h, c))) { continue }
+            let c = cands.choose_weighted(rng, |c| query::hazard(self.f, c).get(*h))
+                .ok()
+                .or_else(|| cands.choose(rng));      // the map may show every candidate as something else
+            if let Some(c) = c { hazards.insert((*h, c)); }
+        }
+
+        for c in unexplained.iter().flat_map(|(_, cands)| cands.iter()) {
+            let s = if hazards.contains(&(Hazard::HOLE, c)) { Sample::HOLE }
+                else if hazards.contains(&(Hazard::TELEPORT, c)) { Sample::TELEPORT }
+                else { Sample::FREE };
+            self.layout.insert(c.clone(), s);
+        }
+    }
+
+    fn sample(&mut self, c: &Coord, rng: &mut StdRng) -> Sample {
+        if let Some(s) = self.layout.get(c) { return s.clone() }
+
+        let s = match query::get(self.f, c) {
+            Position::WALL => Sample::WALL,
+            Position::HOLE => Sample::HOLE,
+            Position::TELEPORT => Sample::TELEPORT,
+            p if p == Position::UNKNOWN || p.is_suspected() => {
+                let h = query::hazard(self.f, c);
+                let r: f64 = rng.gen();
+                if r < h.hole { Sample::HOLE }
+                else if r < h.hole + h.teleport { Sample::TELEPORT }
+                else if p == Position::UNKNOWN && rng.gen_bool(WALL_PRIOR) { Sample::WALL }
+                else { Sample::FREE }
+            },
+            _ => Sample::FREE,
+        };
+        self.layout.insert(c.clone(), s.clone());
+        s
+    }
+}
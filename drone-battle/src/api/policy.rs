@@ -0,0 +1,87 @@
+use serde::Deserialize;
+use serde_json as json;
+
+use crate::api::enums::Action;
+use crate::api::env::{ACTIONS, OBSERVATION_SIZE};
+
+use std::fs;
+use std::io::{Error, ErrorKind};
+
+/// A small neural network, from the observation of `env::encode` to one score for each of `env::ACTIONS`
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Policy {
+    pub layers: Vec<Layer>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Layer {
+    pub weights: Vec<Vec<f32>>,
+    pub bias: Vec<f32>,
+    #[serde(default)]
+    pub activation: Activation,
+}
+
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Activation {
+    #[default]
+    None,
+    Relu,
+    Tanh,
+}
+
+impl Policy {
+    pub fn load(filename: &str) -> Result<Policy, Error> {
+        let content = fs::read_to_string(filename)?;
+        Policy::from_str(content.as_str())
+            .map_err(|e| Error::new(ErrorKind::InvalidData, format!("{}: {}", filename, e)))
+    }
+
+    /// Parses the policy, and checks that the sizes of the layers fit together
+    pub fn from_str(content: &str) -> Result<Policy, String> {
+        let policy: Policy = json::from_str(content).map_err(|e| e.to_string())?;
+        if policy.layers.is_empty() { return Err("no layers".to_string()) }
+
+        let mut inputs = OBSERVATION_SIZE;
+        for (i, l) in policy.layers.iter().enumerate() {
+            if let Some(row) = l.weights.iter().find(|row| row.len() != inputs) {
+                return Err(format!("layer #{}: expected {} inputs, got {}", i, inputs, row.len()))
+            }
+            if l.bias.len() != l.weights.len() {
+                return Err(format!("layer #{}: {} outputs, but {} biases", i, l.weights.len(), l.bias.len()))
+            }
+            inputs = l.weights.len();
+        }
+        if inputs != ACTIONS.len() {
+            return Err(format!("the last layer must have {} outputs, one for each action, got {}", ACTIONS.len(), inputs))
+        }
+        Ok(policy)
+    }
+
+    /// Chooses the action for an observation
+    pub fn act(&self, observation: &[f32]) -> Action {
+        let mut x: Vec<f32> = observation.to_vec();
+        for l in self.layers.iter() {
+            x = l.weights.iter().zip(l.bias.iter())
+                .map(|(row, b)| l.activation.apply(row.iter().zip(x.iter()).map(|(w, v)| w * v).sum::<f32>() + b))
+                .collect();
+        }
+        let best = x.iter().enumerate()
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(i, _)| i)
+            .unwrap_or(ACTIONS.len() - 1);
+        ACTIONS[best].clone()
+    }
+}
+
+impl Activation {
+    fn apply(&self, x: f32) -> f32 {
+        match self {
+            Activation::None => x,
+            Activation::Relu => x.max(0.0),
+            Activation::Tanh => x.tanh(),
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use crate::api::enums::PlayerDirection;
+use crate::api::map::{Coord, Field, Position};
+use crate::api::map::query;
+
+use std::collections::HashMap;
+
+/// Ticks a direction stays checked, as the enemy may walk into it after
+const MEMORY_TICKS: u32 = 4;
+
+/// Turns around to find an enemy heard next to the bot, one direction at a time
+pub struct Search {
+    coord: Option<Coord>,
+    checked: HashMap<PlayerDirection, u32>,
+}
+
+impl Search {
+    pub fn new() -> Search {
+        Search { coord: None, checked: HashMap::new() }
+    }
+
+    /// The bot is at `c` facing `dir`, and there is no enemy in front of it
+    pub fn check(&mut self, c: &Coord, dir: &PlayerDirection) {
+        if self.coord.as_ref() != Some(c) {
+            self.coord = Some(c.clone());
+            self.checked.clear();
+        }
+        self.checked.retain(|_, ticks| { *ticks -= 1; *ticks > 0 });
+        self.checked.insert(dir.clone(), MEMORY_TICKS);
+    }
+
+    /// Next direction to face, where an enemy can be and was not checked yet
+    pub fn next(&mut self, f: &Field, c: &Coord, dir: &PlayerDirection) -> Option<PlayerDirection> {
+        let open: Vec<PlayerDirection> = PlayerDirection::all().into_iter()
+            .filter(|d| d != dir && query::get(f, &c.next(d)) != Position::WALL)
+            .collect();
+        let mut candidates: Vec<&PlayerDirection> = open.iter().filter(|d| !self.checked.contains_key(d)).collect();
+        if candidates.is_empty() {
+            self.checked.retain(|d, _| d == dir);
+            candidates = open.iter().collect();
+        }
+
+        let turns = |d: &PlayerDirection| if *d == dir.opposite() { 2 } else { 1 };
+        candidates.into_iter()
+            .max_by(|a, b| {
+                let (pa, pb) = (query::enemy(f, &c.next(a)), query::enemy(f, &c.next(b)));
+                pa.total_cmp(&pb).then(turns(b).cmp(&turns(a)))
+            })
+            .cloned()
+    }
+
+    /// Directions already checked, to show in the trace
+    pub fn checked(&self) -> Vec<PlayerDirection> {
+        let mut v: Vec<PlayerDirection> = self.checked.keys().cloned().collect();
+        v.sort();
+        v
+    }
+
+    pub fn clear(&mut self) {
+        self.coord = None;
+        self.checked.clear();
+    }
+}
+
+impl Default for Search {
+    fn default() -> Self { Search::new() }
+}
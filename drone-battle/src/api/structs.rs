@@ -5,6 +5,8 @@ use std::fmt;
 use crate::api::enums::{
     PlayerDirection, ServerState,
 };
+use crate::api::map::Coord;
+use crate::api::team::TeamMessage;
 
 #[derive(Debug, Clone)]
 pub struct LastObservation {
@@ -18,6 +20,8 @@ pub struct LastObservation {
     pub is_damage: bool,
     pub is_hit: bool,
     pub distance_enemy_front: i16,
+    /// Last known position of who damaged the bot, if any
+    pub shooter: Option<Coord>,
 
     pub has_read_hit: bool,       // because hit and damage observations are separate
     pub has_read_damage: bool,
@@ -36,6 +40,7 @@ impl LastObservation {
             is_damage: false,
             is_hit: false,
             distance_enemy_front: -1,
+            shooter: None,
             has_read_damage: true,
             has_read_hit: true,
         }
@@ -52,6 +57,7 @@ impl LastObservation {
         self.is_damage = false;
         self.is_hit = false;
         self.distance_enemy_front = -1;
+        self.shooter = None;
         self.has_read_damage = true;
         self.has_read_hit = true;
     }
@@ -109,8 +115,8 @@ pub struct ServerObservation {
 
 #[derive(Debug)]
 pub struct ServerStatus {
-    pub x: i8,
-    pub y: i8,
+    pub x: i16,
+    pub y: i16,
     pub dir: PlayerDirection,
     pub state: ServerState,
     pub score: i64,
@@ -121,8 +127,8 @@ pub struct ServerStatus {
 pub struct ServerPlayer {
     pub node: i64,
     pub name: String,
-    pub x: i8,
-    pub y: i8,
+    pub x: i16,
+    pub y: i16,
     pub dir: PlayerDirection,
     pub state: ServerState,
     pub color: Color
@@ -144,6 +150,11 @@ pub struct ServerNotification {
     pub notification: String
 }
 
+#[derive(Debug)]
+pub struct ServerTeamChat {
+    pub message: TeamMessage
+}
+
 #[derive(Debug)]
 pub struct ServerPlayerNew {
     pub player: String
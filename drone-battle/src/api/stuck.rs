@@ -0,0 +1,118 @@
+use crate::api::enums::{Action, PlayerDirection};
+use crate::api::map::{Coord, Field, Position};
+use crate::api::map::query;
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Moves remembered
+const HISTORY_SIZE: usize = 24;
+/// Longest loop looked for, in moves. A loop must repeat three times to count
+const MAX_LOOP_PERIOD: usize = 4;
+/// Moves that must reach at least `MIN_PROGRESS_BLOCKS` different blocks
+const PROGRESS_MOVES: usize = 20;
+const MIN_PROGRESS_BLOCKS: usize = 3;
+/// Moves in a row into something blocking
+const MAX_BLOCKED: usize = 3;
+/// Ticks a target stays blacklisted
+const BLACKLIST_TICKS: u32 = 50;
+
+/// What the detector found in the recent moves
+#[derive(Clone, Debug, PartialEq)]
+#[allow(non_camel_case_types)]
+pub enum Pattern {
+    // the same poses over and over, like LEFT RIGHT LEFT RIGHT, or going back and forth between two blocks
+    LOOP(usize),
+    // many moves, but only a few different blocks
+    NO_PROGRESS(usize),
+    // walking into walls, one move after another
+    BLOCKED(usize),
+}
+
+impl Pattern {
+    pub fn to_string(&self) -> String {
+        match self {
+            Pattern::LOOP(period) => format!("loop of {} moves", period),
+            Pattern::NO_PROGRESS(blocks) => format!("only {} blocks in the last {} moves", blocks, PROGRESS_MOVES),
+            Pattern::BLOCKED(n) => format!("blocked {} times in a row", n),
+        }
+    }
+}
+
+struct Move {
+    coord: Coord,
+    dir: PlayerDirection,
+    blocked: bool,
+}
+
+/// Watches the moves of the bot, to find when it is stuck, and blacklists the targets that got it stuck
+pub struct StuckDetector {
+    history: VecDeque<Move>,
+    blacklist: HashMap<Coord, u32>,
+}
+
+impl StuckDetector {
+    pub fn new() -> StuckDetector {
+        StuckDetector { history: VecDeque::new(), blacklist: HashMap::new() }
+    }
+
+    /// Adds the pose where the action was chosen. `blocked` tells if the last move was blocked
+    pub fn push(&mut self, c: &Coord, dir: &PlayerDirection, action: &Action, blocked: bool) {
+        self.blacklist.retain(|_, ticks| { *ticks -= 1; *ticks > 0 });
+
+        if !matches!(action, Action::FRONT | Action::BACK | Action::LEFT | Action::RIGHT) { return }
+        if self.history.len() == HISTORY_SIZE { self.history.pop_front(); }
+        self.history.push_back(Move { coord: c.clone(), dir: dir.clone(), blocked });
+    }
+
+    pub fn check(&self) -> Option<Pattern> {
+        let n = self.history.len();
+        let pose = |i: usize| (&self.history[i].coord, &self.history[i].dir);
+
+        let blocked = self.history.iter().rev().take_while(|m| m.blocked).count();
+        if blocked >= MAX_BLOCKED { return Some(Pattern::BLOCKED(blocked)) }
+
+        for period in 2..=MAX_LOOP_PERIOD {
+            let window = 3 * period;
+            if n < window { break }
+            let repeats = (n - window + period..n).all(|i| pose(i) == pose(i - period));
+            let moving = (n - period..n).any(|i| pose(i) != pose(n - 1));
+            if repeats && moving { return Some(Pattern::LOOP(period)) }
+        }
+
+        if n >= PROGRESS_MOVES {
+            let blocks: HashSet<&Coord> = self.history.iter().skip(n - PROGRESS_MOVES).map(|m| &m.coord).collect();
+            if blocks.len() < MIN_PROGRESS_BLOCKS { return Some(Pattern::NO_PROGRESS(blocks.len())) }
+        }
+        None
+    }
+
+    /// Forgets the moves, after recovering
+    pub fn clear(&mut self) { self.history.clear(); }
+
+    pub fn blacklist(&mut self, c: &Coord) { self.blacklist.insert(c.clone(), BLACKLIST_TICKS); }
+
+    pub fn is_blacklisted(&self, c: &Coord) -> bool { self.blacklist.contains_key(c) }
+}
+
+impl Default for StuckDetector {
+    fn default() -> Self { StuckDetector::new() }
+}
+
+/// A random move into a block known to be free, or a random turn if there is none
+pub fn safe_step(f: &Field, c: &Coord, dir: &PlayerDirection, rng: &mut StdRng) -> Action {
+    let free = |d: &PlayerDirection| {
+        let next = c.next(d);
+        matches!(query::get(f, &next), Position::EMPTY | Position::SAFE | Position::GOLD | Position::POWERUP)
+            && !query::is_unsafe(f, &next)
+    };
+    let mut moves: Vec<Action> = Vec::new();
+    if free(dir) { moves.push(Action::FRONT) }
+    if free(&dir.opposite()) { moves.push(Action::BACK) }
+
+    match moves.choose(rng) {
+        Some(a) => a.clone(),
+        None => [Action::LEFT, Action::RIGHT].choose(rng).unwrap().clone(),
+    }
+}
@@ -0,0 +1,293 @@
+pub mod connection;
+pub mod chat;
+
+use serde::{Deserialize, Serialize};
+use serde_json as json;
+
+use crate::api::ai::AI;
+use crate::api::bot::BotData;
+use crate::api::map::{Coord, Field, Position};
+use crate::api::map::{enemies, query, update};
+use crate::api::team::connection::Connection;
+
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Ticks between two messages with all the golds and powerups known
+const ITEMS_TICKS: u64 = 10;
+/// A claim is dropped if the teammate does not send it again in this time
+const CLAIM_TIMEOUT: Duration = Duration::from_secs(3);
+/// Ticks between two messages in the chat, unless the claim changes
+const CHAT_TICKS: u64 = 10;
+
+/// What a drone tells its teammates, once per tick
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TeamMessage {
+    pub from: String,
+    // when it was sent, in ms since the unix epoch
+    pub time: u64,
+    pub x: i16,
+    pub y: i16,
+    // blocks learned since the last message, as (x, y, position)
+    #[serde(default)]
+    pub cells: Vec<(i16, i16, String)>,
+    // golds and powerups known, as (x, y, ms since they were last seen or collected)
+    #[serde(default)]
+    pub golds: Vec<(i16, i16, u64)>,
+    #[serde(default)]
+    pub powerups: Vec<(i16, i16, u64)>,
+    // teleports with a known exit, as (x, y, exit x, exit y)
+    #[serde(default)]
+    pub teleports: Vec<(i16, i16, i16, i16)>,
+    // gold the drone is going after, and the length of its path
+    pub claim: Option<(i16, i16, f64)>,
+}
+
+struct Claim {
+    coord: Coord,
+    cost: f64,
+    received: Instant,
+}
+
+/// Shares what the drones of the same team know about the field, over the local MQTT broker and the game chat
+pub struct Team {
+    connection: Option<Connection>,
+    // key of the messages in the chat, if the chat is used
+    secret: Option<String>,
+    name: String,
+    // what was already sent of each block
+    published: HashMap<Coord, Position>,
+    published_teleports: HashSet<(Coord, Coord)>,
+    ticks: u64,
+    claims: HashMap<String, Claim>,
+    // time of the last message of each teammate, to drop the repeated and the late ones
+    last_time: HashMap<String, u64>,
+    // tick and claim of the last message in the chat
+    last_chat: Option<(u64, Option<Coord>)>,
+}
+
+impl Team {
+    /// Joins the broker if `team` is given, and the chat if `secret` is given. `None` if neither can be used
+    pub fn new(name: String, team: Option<String>, secret: Option<String>) -> Option<Team> {
+        let connection = match team {
+            Some(team) => {
+                let mut c = Connection::new(name.clone(), team);
+                if c.connect().is_some() {
+                    println!("[TEAM] sucessful connection");
+                    Some(c)
+                } else {
+                    println!("[TEAM] could not connect to mqtt");
+                    None
+                }
+            },
+            None => None,
+        };
+        if connection.is_none() && secret.is_none() { return None }
+
+        Some(Team {
+            connection,
+            secret,
+            name,
+            published: HashMap::new(),
+            published_teleports: HashSet::new(),
+            ticks: 0,
+            claims: HashMap::new(),
+            last_time: HashMap::new(),
+            last_chat: None,
+        })
+    }
+
+    /// Sends what the bot learned and merges what the teammates sent. Returns the line to say in the chat, if any
+    pub fn exchange(&mut self, ai: &mut AI, bot: &BotData) -> Option<String> {
+        let claim = ai.claim();
+        let message = self.message(&ai.field, bot, claim.as_ref());
+
+        if let Some(connection) = self.connection.as_mut() {
+            match json::to_string(&message) {
+                Ok(payload) => { if connection.send(payload).is_none() { println!("[TEAM] could not send update") } },
+                Err(e) => println!("[TEAM] could not encode update: {}", e),
+            }
+        }
+
+        let received = self.connection.as_mut().map_or(Vec::new(), |c| c.receive());
+        for payload in received {
+            match json::from_str::<TeamMessage>(&payload) {
+                Ok(m) => ai.pending_change |= self.receive(&mut ai.field, m),
+                Err(e) => println!("[TEAM] invalid message: {}", e),
+            }
+        }
+        self.allocate(&mut ai.field, claim.as_ref());
+        self.chat(&ai.field, message)
+    }
+
+    /// Returns `true` if the map has changed
+    pub fn receive(&mut self, f: &mut Field, m: TeamMessage) -> bool {
+        if m.from == self.name { return false }
+        if self.last_time.get(&m.from).is_some_and(|t| *t >= m.time) { return false }
+        self.last_time.insert(m.from.clone(), m.time);
+
+        let grown = f.fit(&Coord { x: m.x, y: m.y });
+        let changed = merge(f, &m, Duration::from_millis(now_millis().saturating_sub(m.time)));
+        enemies::absent(f, &Coord { x: m.x, y: m.y });
+        match m.claim {
+            Some((x, y, cost)) => {
+                self.claims.insert(m.from.clone(), Claim { coord: Coord { x, y }, cost, received: Instant::now() });
+            },
+            None => { self.claims.remove(&m.from); }
+        }
+        grown || changed
+    }
+
+    /// Some message of the team came from the player with this name
+    pub fn is_teammate(&self, name: &str) -> bool { self.last_time.contains_key(name) }
+
+    /// The message as a line for the chat, every `CHAT_TICKS` ticks or when the claim changes
+    fn chat(&mut self, f: &Field, mut message: TeamMessage) -> Option<String> {
+        let secret = self.secret.as_ref()?;
+        let claim = message.claim.map(|(x, y, _)| Coord { x, y });
+        if let Some((tick, last)) = &self.last_chat {
+            if self.ticks - tick < CHAT_TICKS && *last == claim { return None }
+        }
+        self.last_chat = Some((self.ticks, claim));
+
+        // only the golds there now, as the chat is short
+        message.golds = f.gold_positions.iter()
+            .filter(|(_, t)| **t >= f.config.spawn_timer)
+            .map(|(c, t)| (c.x, c.y, t.as_millis() as u64))
+            .collect();
+        Some(chat::encode(&message, secret))
+    }
+
+    /// Reserves the golds claimed by teammates, unless the bot is closer to them
+    fn allocate(&mut self, f: &mut Field, claim: Option<&(Coord, f64)>) {
+        self.claims.retain(|_, c| c.received.elapsed() < CLAIM_TIMEOUT);
+        f.reserved.clear();
+        for (from, c) in self.claims.iter() {
+            let mine = claim.is_some_and(|(coord, cost)| {
+                *coord == c.coord && (*cost < c.cost || (*cost == c.cost && self.name < *from))
+            });
+            if !mine { f.reserved.insert(c.coord.clone(), from.clone()); }
+        }
+    }
+
+    fn message(&mut self, f: &Field, bot: &BotData, claim: Option<&(Coord, f64)>) -> TeamMessage {
+        self.ticks += 1;
+
+        // only what the bot learned by itself, so messages do not echo between teammates
+        let mut cells: Vec<(i16, i16, String)> = Vec::new();
+        for (c, p) in f.grid.known() {
+            let shared = matches!(p, Position::WALL | Position::HOLE | Position::TELEPORT | Position::EMPTY | Position::SAFE);
+            if !shared || f.provenance.contains_key(&c) || self.published.get(&c) == Some(p) { continue }
+            cells.push((c.x, c.y, p.to_string()));
+            self.published.insert(c, p.clone());
+        }
+
+        let mut teleports: Vec<(i16, i16, i16, i16)> = Vec::new();
+        for entry in f.teleports.keys() {
+            if let Some(exit) = query::teleport_exit(f, entry) {
+                if self.published_teleports.insert((entry.clone(), exit.clone())) {
+                    teleports.push((entry.x, entry.y, exit.x, exit.y));
+                }
+            }
+        }
+
+        let items = |hm: &HashMap<Coord, Duration>| -> Vec<(i16, i16, u64)> {
+            hm.iter().map(|(c, t)| (c.x, c.y, t.as_millis() as u64)).collect()
+        };
+        let send_items = self.ticks % ITEMS_TICKS == 1;
+
+        TeamMessage {
+            from: self.name.clone(),
+            time: now_millis(),
+            x: bot.get_x(),
+            y: bot.get_y(),
+            cells,
+            golds: if send_items { items(&f.gold_positions) } else { Vec::new() },
+            powerups: if send_items { items(&f.powerup_positions) } else { Vec::new() },
+            teleports,
+            claim: claim.map(|(c, cost)| (c.x, c.y, *cost)),
+        }
+    }
+
+    /// Forgets what was sent, after the field restarts
+    pub fn restart(&mut self) {
+        self.published.clear();
+        self.published_teleports.clear();
+        self.claims.clear();
+        self.last_chat = None;
+    }
+
+    pub fn close(self) {
+        if let Some(c) = self.connection { c.disconnect(); }
+    }
+}
+
+/// Merges what a teammate knows into the field, keeping the blocks the bot already knows better
+pub fn merge(f: &mut Field, m: &TeamMessage, elapsed: Duration) -> bool {
+    let mut changed = false;
+
+    for (x, y, p) in m.cells.iter() {
+        let c = Coord { x: *x, y: *y };
+        let p = match Position::from_str(p) {
+            Some(p) => p,
+            None => continue,
+        };
+        let current = query::get(f, &c);
+        let unknown = current == Position::UNKNOWN || current.is_suspected();
+        let learned = match p {
+            Position::WALL => unknown || current == Position::SAFE,
+            Position::HOLE | Position::TELEPORT | Position::SAFE => unknown,
+            Position::EMPTY => unknown || current == Position::SAFE,
+            _ => false,
+        };
+        if !learned { continue }
+        if p != Position::WALL { f.fit(&c); }
+
+        // the teammate walked there, so it is not a frontier anymore
+        if p == Position::EMPTY || p == Position::WALL {
+            update::remove_safe(f, &c);
+            f.grid.set(&c, p);
+        } else {
+            update::set(f, c.clone(), p, false);
+        }
+        f.provenance.insert(c, m.from.clone());
+        changed = true;
+    }
+
+    for (x, y, ex, ey) in m.teleports.iter() {
+        let entry = Coord { x: *x, y: *y };
+        let exit = Coord { x: *ex, y: *ey };
+        if query::teleport_exit(f, &entry).as_ref() == Some(&exit) { continue }
+        update::set_teleport(f, entry.clone(), exit);
+        f.provenance.entry(entry).or_insert(m.from.clone());
+        changed = true;
+    }
+
+    // the most recent sighting wins
+    let items = [(&m.golds, Position::GOLD), (&m.powerups, Position::POWERUP)];
+    for (list, p) in items {
+        for (x, y, ms) in list.iter() {
+            let c = Coord { x: *x, y: *y };
+            f.fit(&c);
+            let timer = Duration::from_millis(*ms) + elapsed;
+            let hm = if p == Position::GOLD { &mut f.gold_positions } else { &mut f.powerup_positions };
+            match hm.get_mut(&c) {
+                Some(t) if *t <= timer => continue,
+                Some(t) => *t = timer,
+                None => { hm.insert(c.clone(), timer); }
+            }
+            let current = query::get(f, &c);
+            if current == Position::UNKNOWN || current == Position::SAFE || current.is_suspected() {
+                update::remove_safe(f, &c);
+                f.grid.set(&c, p.clone());
+                f.provenance.insert(c, m.from.clone());
+            }
+            changed = true;
+        }
+    }
+    changed
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis() as u64)
+}
@@ -0,0 +1,153 @@
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::api::team::TeamMessage;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Start of every team message in the chat
+pub const PREFIX: &str = "DB1|";
+/// Bytes of the HMAC kept in the message
+const TAG_BYTES: usize = 8;
+
+/// The position, golds and claim of a message as `DB1|name|time|x.y|golds|claim|tag`, signed with the secret
+pub fn encode(m: &TeamMessage, secret: &str) -> String {
+    let golds: Vec<String> = m.golds.iter().map(|(x, y, ms)| format!("{}.{}.{}", x, y, ms / 1000)).collect();
+    let claim = match m.claim {
+        Some((x, y, cost)) => format!("{}.{}.{}", x, y, cost.round() as i64),
+        None => String::new(),
+    };
+    let body = format!("{}{}|{}|{}.{}|{}|{}", PREFIX, m.from, m.time, m.x, m.y, golds.join(","), claim);
+    let tag: String = sign(&body, secret).iter().take(TAG_BYTES).map(|b| format!("{:02x}", b)).collect();
+    format!("{}|{}", body, tag)
+}
+
+/// Team message in a notification from the server, if there is one signed with the same secret
+pub fn decode(notification: &str, secret: &str) -> Option<TeamMessage> {
+    let start = notification.find(PREFIX)?;
+    let line = notification[start..].trim();
+    let (body, tag) = line.rsplit_once('|')?;
+    if !verify(body, tag, secret) { return None }
+
+    // the name is what is left, so it may have a '|'
+    let mut fields = body[PREFIX.len()..].rsplitn(5, '|');
+    let claim = fields.next()?;
+    let golds = fields.next()?;
+    let (x, y) = pair(fields.next()?)?;
+    let time = fields.next()?.parse::<u64>().ok()?;
+    let from = fields.next()?.to_string();
+
+    let mut g: Vec<(i16, i16, u64)> = Vec::new();
+    for s in golds.split(',').filter(|s| !s.is_empty()) {
+        let (x, y, secs) = triple(s)?;
+        g.push((x, y, secs.max(0) as u64 * 1000));
+    }
+    let claim = match claim {
+        "" => None,
+        s => { let (x, y, cost) = triple(s)?; Some((x, y, cost as f64)) }
+    };
+
+    Some(TeamMessage {
+        from, time, x, y,
+        cells: Vec::new(),
+        golds: g,
+        powerups: Vec::new(),
+        teleports: Vec::new(),
+        claim,
+    })
+}
+
+fn sign(body: &str, secret: &str) -> Vec<u8> {
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).expect("hmac takes keys of any size");
+    mac.update(body.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn verify(body: &str, tag: &str, secret: &str) -> bool {
+    if tag.len() != 2 * TAG_BYTES || !tag.is_ascii() { return false }
+    let bytes: Option<Vec<u8>> = (0..TAG_BYTES).map(|i| u8::from_str_radix(&tag[2 * i..2 * i + 2], 16).ok()).collect();
+    let bytes = match bytes { Some(b) => b, None => return false };
+
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).expect("hmac takes keys of any size");
+    mac.update(body.as_bytes());
+    mac.verify_truncated_left(&bytes).is_ok()
+}
+
+fn pair(s: &str) -> Option<(i16, i16)> {
+    let (x, y) = s.split_once('.')?;
+    Some((x.parse().ok()?, y.parse().ok()?))
+}
+
+fn triple(s: &str) -> Option<(i16, i16, i64)> {
+    let mut it = s.splitn(3, '.');
+    Some((it.next()?.parse().ok()?, it.next()?.parse().ok()?, it.next()?.parse().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "segredo";
+
+    fn message(from: &str) -> TeamMessage {
+        TeamMessage {
+            from: from.to_string(),
+            time: 1_700_000_000_123,
+            x: 12,
+            y: -3,
+            cells: Vec::new(),
+            golds: vec![(4, 5, 18000), (0, 33, 0)],
+            powerups: Vec::new(),
+            teleports: Vec::new(),
+            claim: Some((4, 5, 7.0)),
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let m = message("drone");
+        let line = encode(&m, SECRET);
+        let d = decode(&format!("[chat] drone: {}\n", line), SECRET).expect("signed with the same secret");
+
+        assert_eq!(d.from, m.from);
+        assert_eq!(d.time, m.time);
+        assert_eq!((d.x, d.y), (m.x, m.y));
+        assert_eq!(d.golds, m.golds);
+        assert_eq!(d.claim, m.claim);
+
+        let none = TeamMessage { claim: None, golds: Vec::new(), ..message("drone") };
+        let d = decode(&encode(&none, SECRET), SECRET).unwrap();
+        assert_eq!(d.claim, None);
+        assert!(d.golds.is_empty());
+    }
+
+    #[test]
+    fn tampered_tag() {
+        let line = encode(&message("drone"), SECRET);
+        let (body, tag) = line.rsplit_once('|').unwrap();
+
+        let mut flipped = tag.to_string();
+        flipped.replace_range(..1, if tag.starts_with('0') { "1" } else { "0" });
+        assert!(decode(&format!("{}|{}", body, flipped), SECRET).is_none());
+        assert!(decode(&format!("{}|{}", body, &tag[..tag.len() - 2]), SECRET).is_none());
+        assert!(decode(&format!("{}|zz{}", body, &tag[2..]), SECRET).is_none());
+        // the body changed, but not the tag
+        assert!(decode(&line.replacen("|12.-3|", "|13.-3|", 1), SECRET).is_none());
+    }
+
+    #[test]
+    fn wrong_secret() {
+        let line = encode(&message("drone"), SECRET);
+        assert!(decode(&line, "outro").is_none());
+        assert!(decode(&line, "").is_none());
+    }
+
+    #[test]
+    fn name_with_separator() {
+        let m = message("equipe|1");
+        let d = decode(&encode(&m, SECRET), SECRET).unwrap();
+        assert_eq!(d.from, "equipe|1");
+        assert_eq!((d.x, d.y), (m.x, m.y));
+        assert_eq!(d.claim, m.claim);
+    }
+}
@@ -0,0 +1,71 @@
+extern crate paho_mqtt as mqtt;
+
+use std::sync::mpsc::Receiver;
+use std::time::Duration;
+use mqtt::{Client, Message, CreateOptionsBuilder, ConnectOptionsBuilder};
+
+/// Channel of a team in the local MQTT broker, the same used by the graphics
+pub struct Connection {
+    mqtt_id: String,
+    topic: String,
+    client: Option<Client>,
+    rx: Option<Receiver<Option<Message>>>,
+}
+
+impl Connection {
+    pub fn new(identifier: String, team: String) -> Connection {
+        Connection {
+            mqtt_id: format!("team-{}", identifier),
+            topic: format!("puc-drone-battle-rust/team/{}", team),
+            client: None,
+            rx: None,
+        }
+    }
+
+    pub fn connect(&mut self) -> Option<()> {
+        let create_ops = CreateOptionsBuilder::new()
+            .server_uri("tcp://localhost:1883")
+            .client_id(self.mqtt_id.clone())
+            .persistence(None)
+            .finalize();
+
+        let mut cli = Client::new(create_ops).ok()?;
+        // must be started before connecting, to not lose any message
+        let rx = cli.start_consuming();
+
+        let connect_ops = ConnectOptionsBuilder::new()
+            .connect_timeout(Duration::from_secs(1))
+            .finalize();
+
+        if let Err(e) = cli.connect(connect_ops) {
+            println!("[TEAM] unable to connect: {:?}", e);
+            return None;
+        }
+        if let Err(e) = cli.subscribe(&self.topic, 0) {
+            println!("[TEAM] unable to subscribe: {:?}", e);
+            return None;
+        }
+
+        self.client = Some(cli);
+        self.rx = Some(rx);
+        Some(())
+    }
+
+    pub fn send(&mut self, payload: String) -> Option<()> {
+        let msg = Message::new(self.topic.clone(), payload, 0);
+        self.client.as_ref()?.publish(msg).ok()?;
+        Some(())
+    }
+
+    /// Messages received since the last call, without waiting
+    pub fn receive(&mut self) -> Vec<String> {
+        match &self.rx {
+            Some(rx) => rx.try_iter().flatten().map(|m| m.payload_str().to_string()).collect(),
+            None => Vec::new()
+        }
+    }
+
+    pub fn disconnect(self) {
+        if let Some(c) = self.client { c.disconnect(None).ok(); }
+    }
+}
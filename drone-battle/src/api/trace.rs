@@ -0,0 +1,88 @@
+use serde::Serialize;
+use serde_json as json;
+
+use std::fs::File;
+use std::io::{self, LineWriter, Write};
+
+/// Why the AI did what it did in one tick
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct DecisionTrace {
+    pub tick: u64,
+    pub x: i16,
+    pub y: i16,
+    pub dir: String,
+    pub energy: i32,
+    // percepts, queries and utility inputs read while deciding, with their values
+    pub inputs: Vec<(String, String)>,
+    // rules of the decision table (or behaviours of the utility AI) tested, in order
+    pub rules: Vec<RuleTrace>,
+    pub state: String,
+    // targets considered by the state, with the cost of the path to each one
+    pub candidates: Vec<Candidate>,
+    // the action came from the path of the last tick, without planning again
+    pub cached_path: bool,
+    // the path of the last tick was repaired where the map changed, keeping its destination
+    pub repaired_path: bool,
+    // loop found by the stuck detector, if any
+    pub recovery: Option<String>,
+    pub action: String,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct RuleTrace {
+    pub name: String,
+    pub matched: bool,
+    // why the rule did not match, or the score of the behaviour
+    pub reason: Option<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct Candidate {
+    // what the target is: frontier, stop, powerup
+    pub kind: String,
+    pub x: i16,
+    pub y: i16,
+    pub cost: f64,
+    // score of the target, when it has one
+    pub value: Option<f64>,
+}
+
+impl DecisionTrace {
+    /// Records an input, once: explaining the rules reads the same inputs again
+    pub fn input(&mut self, name: &str, value: impl ToString) {
+        if self.inputs.iter().any(|(n, _)| n == name) { return }
+        self.inputs.push((name.to_string(), value.to_string()));
+    }
+
+    /// One line, as in `EXPLORE by 'explore' after 6 rules -> FRONT (cached path, 3 candidates)`
+    pub fn summary(&self) -> String {
+        let fired = self.rules.iter().position(|r| r.matched);
+        let by = match fired {
+            Some(i) => format!(" by '{}' after {} rules", self.rules[i].name, i),
+            None => String::new(),
+        };
+        let mut notes: Vec<String> = Vec::new();
+        if self.cached_path { notes.push("cached path".to_string()) }
+        if self.repaired_path { notes.push("repaired path".to_string()) }
+        if !self.candidates.is_empty() { notes.push(format!("{} candidates", self.candidates.len())) }
+        if let Some(r) = &self.recovery { notes.push(format!("stuck: {}", r)) }
+
+        let notes = if notes.is_empty() { String::new() } else { format!(" ({})", notes.join(", ")) };
+        format!("{}{} -> {}{}", self.state, by, self.action, notes)
+    }
+}
+
+/// File with the traces of a match, one JSON for each line
+pub struct TraceLog {
+    out: LineWriter<File>,
+}
+
+impl TraceLog {
+    pub fn create(filename: &str) -> io::Result<TraceLog> {
+        Ok(TraceLog { out: LineWriter::new(File::create(filename)?) })
+    }
+
+    pub fn write(&mut self, trace: &DecisionTrace) -> io::Result<()> {
+        writeln!(self.out, "{}", json::to_string(trace)?)
+    }
+}
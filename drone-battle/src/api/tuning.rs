@@ -0,0 +1,142 @@
+use serde::Serialize;
+use serde_json as json;
+
+use crate::api::arena::{self, MatchResult};
+use crate::api::config::{Config, Parameters};
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::fs;
+use std::io::Error;
+use std::thread;
+
+/// Range searched for each parameter, and if it only takes whole numbers
+const RANGES: [(&str, f64, f64, bool); 12] = [
+    ("recharge_on_powerup_energy", 40.0, 100.0, true),
+    ("recharge_energy", 30.0, 95.0, true),
+    ("min_attack_energy", 0.0, 60.0, true),
+    ("run_energy", 0.0, 60.0, true),
+    ("ticks_running", 1.0, 10.0, true),
+    ("max_ticks_attacking", 3.0, 20.0, true),
+    ("back_penalty", 0.0, 4.0, false),
+    ("safe_bonus", 0.3, 1.0, false),
+    ("unsafe_penalty", 0.0, 30.0, false),
+    ("hunt_energy", 0.0, 60.0, true),
+    ("min_hunt_energy", 30.0, 100.0, true),
+    ("max_ticks_hunting", 5.0, 60.0, true),
+];
+
+/// Chance of sampling a whole new candidate, instead of changing the best one a bit
+const EXPLORE_CHANCE: f64 = 0.5;
+/// How much of a range a change of the best candidate can move
+const STEP_SIZE: f64 = 0.2;
+
+pub struct TuneOptions {
+    pub iterations: u32,
+    pub matches: u32,
+    pub ticks: u32,
+    pub seed: u64,
+    pub output: String,
+}
+
+impl TuneOptions {
+    pub fn default() -> TuneOptions {
+        TuneOptions { iterations: 20, matches: 6, ticks: 1500, seed: 0, output: "tuned_parameters.json".to_string() }
+    }
+}
+
+/// Scores of a parameter set over the matches
+#[derive(Serialize, Clone, Debug)]
+pub struct Summary {
+    pub mean: f64,
+    pub std: f64,
+    pub min: i64,
+    pub max: i64,
+    pub scores: Vec<i64>,
+    // mean of golds collected and deaths in a match
+    pub golds: f64,
+    pub deaths: f64,
+}
+
+#[derive(Serialize)]
+struct TuneResult {
+    parameters: Parameters,
+    scores: Summary,
+    // the parameters of the config, for comparison
+    baseline: Summary,
+}
+
+impl Summary {
+    fn new(results: Vec<MatchResult>) -> Summary {
+        let n = results.len().max(1) as f64;
+        let scores: Vec<i64> = results.iter().map(|r| r.score).collect();
+        let mean = scores.iter().sum::<i64>() as f64 / n;
+        let var = scores.iter().map(|s| (*s as f64 - mean).powi(2)).sum::<f64>() / n;
+        Summary {
+            mean,
+            std: var.sqrt(),
+            min: scores.iter().copied().min().unwrap_or(0),
+            max: scores.iter().copied().max().unwrap_or(0),
+            scores,
+            golds: results.iter().map(|r| r.golds as f64).sum::<f64>() / n,
+            deaths: results.iter().map(|r| r.deaths as f64).sum::<f64>() / n,
+        }
+    }
+}
+
+/// Random search for the parameters that score the most in the same matches of the local arena
+pub fn tune(config: &Config, options: &TuneOptions) -> Result<(), Error> {
+    let mut rng = StdRng::seed_from_u64(options.seed);
+
+    let baseline = evaluate(config, &config.params, options);
+    println!("[TUNE] baseline: mean {:.1} | std {:.1} | golds {:.1} | deaths {:.1}",
+             baseline.mean, baseline.std, baseline.golds, baseline.deaths);
+    let mut best = (config.params.clone(), baseline.clone());
+
+    for i in 0..options.iterations {
+        let candidate = if rng.gen_bool(EXPLORE_CHANCE) { sample(&mut rng) } else { mutate(&best.0, &mut rng) };
+        let summary = evaluate(config, &candidate, options);
+        let improved = summary.mean > best.1.mean;
+        println!("[TUNE] #{}: mean {:.1} | std {:.1}{}", i, summary.mean, summary.std, if improved { " | best" } else { "" });
+        if improved { best = (candidate, summary) }
+    }
+
+    let result = TuneResult { parameters: best.0, scores: best.1, baseline };
+    fs::write(&options.output, json::to_string_pretty(&result)?)?;
+    println!("[TUNE] best: mean {:.1} | saved to {}", result.scores.mean, options.output);
+    Ok(())
+}
+
+/// Plays all matches with the parameters, one thread for each
+fn evaluate(config: &Config, params: &Parameters, options: &TuneOptions) -> Summary {
+    let mut config = config.clone();
+    config.params = params.clone();
+    let config = &config;
+
+    let results: Vec<MatchResult> = thread::scope(|s| {
+        let handles: Vec<_> = (0..options.matches)
+            .map(|m| s.spawn(move || arena::run_match(config, options.seed + m as u64, options.ticks)))
+            .collect();
+        handles.into_iter().map(|h| h.join().expect("match panicked")).collect()
+    });
+    Summary::new(results)
+}
+
+fn sample(rng: &mut StdRng) -> Parameters {
+    let mut params = Parameters::default();
+    for (name, min, max, integer) in RANGES.iter() {
+        let v = rng.gen_range(*min..=*max);
+        params.set(name, if *integer { v.round() } else { v });
+    }
+    params
+}
+
+fn mutate(params: &Parameters, rng: &mut StdRng) -> Parameters {
+    let mut new = params.clone();
+    for (name, min, max, integer) in RANGES.iter() {
+        let step = (max - min) * STEP_SIZE;
+        let v = (params.get(name).unwrap_or(*min) + rng.gen_range(-step..=step)).clamp(*min, *max);
+        new.set(name, if *integer { v.round() } else { v });
+    }
+    new
+}
@@ -0,0 +1,231 @@
+use serde::Deserialize;
+use serde_json as json;
+
+use crate::api::ai::BotState;
+
+use std::fs;
+use std::io::{Error, ErrorKind};
+
+/// Curves used when none is given in the config
+const DEFAULT_UTILITY: &str = include_str!("../../utility.json");
+
+/// Distances (in steps) and counts used to bring the inputs between 0 and 1
+pub const MAX_DISTANCE: f64 = 50.0;
+pub const MAX_SAFE_BLOCKS: f64 = 50.0;
+pub const MAX_TICKS_ATTACKING: f64 = 10.0;
+pub const MAX_TICKS_HUNTING: f64 = 30.0;
+
+/// Behaviours of the utility AI, where the one with the highest score runs
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct UtilityConfig {
+    // added to the score of the behaviour of the last tick, so the bot does not keep switching between two
+    pub hysteresis: f64,
+    pub behaviours: Vec<Behaviour>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Behaviour {
+    pub behaviour: BehaviourKind,
+    // times the curve of each consideration
+    pub weight: f64,
+    #[serde(default)]
+    pub considerations: Vec<Consideration>,
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum BehaviourKind {
+    Collect,
+    Recharge,
+    Explore,
+    Attack,
+    Flee,
+    Hunt,
+    Search,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Consideration {
+    pub input: Input,
+    pub curve: Curve,
+}
+
+/// Values read from the bot and the field, all between 0 and 1
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Input {
+    /// Energy / 100
+    Energy,
+    /// 1 if there is a route to collect some gold, or if standing on one
+    GoldRoute,
+    /// Steps to the first target of `route::closest` / 50, 1 if there is none, 0 if standing on a gold
+    GoldDistance,
+    /// Steps to the closest powerup that will be ready on arrival / 50, 1 if there is none, 0 if standing on one
+    PowerupDistance,
+    /// 1 if there is an enemy in front, without a wall between
+    EnemyFront,
+    /// 1 if took damage, 0.7 if there is an enemy in front, 0.5 if heard steps
+    Threat,
+    /// Ticks attacking in a row / 10
+    TicksAttacking,
+    /// Safe blocks not explored yet / 50
+    SafeBlocks,
+    /// 1 if some opponent has `hunt_energy` or less, and an enemy is probably nearby
+    HuntTarget,
+    /// Ticks hunting the same enemy / 30
+    TicksHunting,
+    /// 1 if heard steps, without an enemy in front
+    Steps,
+}
+
+/// Score of a behaviour in a tick, with the value of the curve of each of its considerations
+#[derive(Clone, Debug)]
+pub struct Score {
+    pub behaviour: BehaviourKind,
+    pub score: f64,
+    /// Stops at the consideration that brought the score to 0, as the others are not read
+    pub considerations: Vec<(Input, f64)>,
+}
+
+/// A response curve, mapping an input to a score between 0 and 1
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Curve {
+    pub kind: CurveKind,
+    // slope
+    #[serde(default = "one")]
+    pub m: f64,
+    // exponent
+    #[serde(default = "one")]
+    pub k: f64,
+    // vertical shift
+    #[serde(default)]
+    pub b: f64,
+    // horizontal shift
+    #[serde(default)]
+    pub c: f64,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum CurveKind {
+    // m * (x - c) + b
+    Linear,
+    // m * (x - c)^k + b
+    Polynomial,
+    // k / (1 + e^(-m * (x - c))) + b
+    Logistic,
+    // m + b if x >= c, else b
+    Step,
+}
+
+fn one() -> f64 { 1.0 }
+
+impl Curve {
+    pub fn eval(&self, x: f64) -> f64 {
+        let y = match self.kind {
+            CurveKind::Linear => self.m * (x - self.c) + self.b,
+            CurveKind::Polynomial => self.m * (x - self.c).powf(self.k) + self.b,
+            CurveKind::Logistic => self.k / (1.0 + (-self.m * (x - self.c)).exp()) + self.b,
+            CurveKind::Step => if x >= self.c { self.m + self.b } else { self.b },
+        };
+        if y.is_nan() { 0.0 } else { y.clamp(0.0, 1.0) }
+    }
+}
+
+impl BehaviourKind {
+    pub fn state(&self) -> BotState {
+        match self {
+            BehaviourKind::Collect => BotState::COLLECT,
+            BehaviourKind::Recharge => BotState::RECHARGE,
+            BehaviourKind::Explore => BotState::EXPLORE,
+            BehaviourKind::Attack => BotState::ATTACK,
+            BehaviourKind::Flee => BotState::RUN,
+            BehaviourKind::Hunt => BotState::HUNT,
+            BehaviourKind::Search => BotState::SEARCH,
+        }
+    }
+
+    pub fn to_string(&self) -> String {
+        match self {
+            BehaviourKind::Collect => "collect".to_string(),
+            BehaviourKind::Recharge => "recharge".to_string(),
+            BehaviourKind::Explore => "explore".to_string(),
+            BehaviourKind::Attack => "attack".to_string(),
+            BehaviourKind::Flee => "flee".to_string(),
+            BehaviourKind::Hunt => "hunt".to_string(),
+            BehaviourKind::Search => "search".to_string(),
+        }
+    }
+}
+
+impl Input {
+    pub fn to_string(&self) -> String {
+        match self {
+            Input::Energy => "energy".to_string(),
+            Input::GoldRoute => "gold_route".to_string(),
+            Input::GoldDistance => "gold_distance".to_string(),
+            Input::PowerupDistance => "powerup_distance".to_string(),
+            Input::EnemyFront => "enemy_front".to_string(),
+            Input::Threat => "threat".to_string(),
+            Input::TicksAttacking => "ticks_attacking".to_string(),
+            Input::SafeBlocks => "safe_blocks".to_string(),
+            Input::HuntTarget => "hunt_target".to_string(),
+            Input::TicksHunting => "ticks_hunting".to_string(),
+            Input::Steps => "steps".to_string(),
+        }
+    }
+}
+
+impl Score {
+    /// As `collect=0.81 (gold_route 1.00, gold_distance 0.81)`
+    pub fn to_string(&self) -> String {
+        let considerations: Vec<String> = self.considerations.iter()
+            .map(|(input, value)| format!("{} {:.2}", input.to_string(), value))
+            .collect();
+        format!("{}={:.2} ({})", self.behaviour.to_string(), self.score, considerations.join(", "))
+    }
+}
+
+impl UtilityConfig {
+    pub fn default() -> UtilityConfig {
+        UtilityConfig::from_str(DEFAULT_UTILITY).expect("invalid default utility config")
+    }
+
+    pub fn load(filename: &str) -> Result<UtilityConfig, Error> {
+        let content = fs::read_to_string(filename)?;
+        UtilityConfig::from_str(content.as_str())
+            .map_err(|e| Error::new(ErrorKind::InvalidData, format!("{}: {}", filename, e)))
+    }
+
+    /// Errors point to the offending behaviour, by its position and name
+    pub fn from_str(content: &str) -> Result<UtilityConfig, String> {
+        let value: json::Value = json::from_str(content).map_err(|e| e.to_string())?;
+        let raw_behaviours = value.get("behaviours")
+            .and_then(|b| b.as_array())
+            .ok_or("missing \"behaviours\" list")?;
+
+        // parsing each behaviour alone first, to know where the error is
+        for (i, raw) in raw_behaviours.iter().enumerate() {
+            Behaviour::deserialize(raw).map_err(|e| {
+                let name = raw.get("behaviour").and_then(|n| n.as_str()).unwrap_or("?");
+                format!("behaviour #{} '{}': {}", i, name, e)
+            })?;
+        }
+        let config: UtilityConfig = json::from_value(value).map_err(|e| e.to_string())?;
+
+        for (i, b) in config.behaviours.iter().enumerate() {
+            let error = |msg: &str| format!("behaviour #{} '{}': {}", i, b.behaviour.to_string(), msg);
+            if b.weight < 0.0 { return Err(error("negative weight")) }
+            if config.behaviours[..i].iter().any(|other| other.behaviour == b.behaviour) {
+                return Err(error("duplicated behaviour"))
+            }
+        }
+        if config.behaviours.is_empty() { return Err("no behaviours".to_string()) }
+        if config.hysteresis < 0.0 { return Err("negative hysteresis".to_string()) }
+        Ok(config)
+    }
+}
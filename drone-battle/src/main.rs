@@ -3,24 +3,30 @@ mod api;
 use crate::api::config::Config;
 use crate::api::bot::Bot;
 use crate::api::graphics::Graphics;
+use crate::api::team::Team;
+use crate::api::tuning::{self, TuneOptions};
+use crate::api::env;
+use crate::api::bench::{self, BenchOptions};
+use crate::api::map::{distance, frontier, text, Coord};
 
 use std::env::args;
+use std::fs;
 
 fn main() {
     // reading args
     let args: Vec<String> = args().collect();
+    match args.get(1).map(|a| a.as_str()) {
+        Some("tune") => return tune(&args[2..]),
+        Some("env") => return serve_env(&args[2..]),
+        Some("bench") => return bench(&args[2..]),
+        Some("path") => return path(&args[2..]),
+        _ => {}
+    }
+
     let config;
     if args.len() == 2 {
         let path = args.get(1).unwrap().clone();
-        // loading config from file
-        config = match Config::load(path) {
-            Ok(c) => c,
-            Err(e) => {
-                println!("Error loading from config file: {}", e);
-                println!("Using default configuration");
-                Config::default()
-            }
-        }
+        config = load_config(path);
     } else {
         println!("Using default configuration");
         config = Config::default();
@@ -35,9 +41,133 @@ fn main() {
     };
     println!("Graphics is set to {}", graphics.is_some());
 
-    let mut bot = Bot::new(config, graphics);
+    let team = Team::new(config.name.clone(), config.team.clone(), config.chat_secret.clone());
+    println!("Team is set to {}", team.is_some());
+
+    let mut bot = Bot::new(config, graphics, team);
     bot.run();
 
     bot.exit();
     println!("---- CLOSING ----");
 }
+
+fn load_config(path: String) -> Config {
+    // loading config from file
+    match Config::load(path) {
+        Ok(c) => c,
+        Err(e) => {
+            println!("Error loading from config file: {}", e);
+            println!("Using default configuration");
+            Config::default()
+        }
+    }
+}
+
+/// `tune [config] [--iterations N] [--matches N] [--ticks N] [--seed N] [--output file]`
+fn tune(args: &[String]) {
+    let mut options = TuneOptions::default();
+    let mut config = None;
+
+    let mut it = args.iter();
+    while let Some(arg) = it.next() {
+        let value = |v: Option<&String>| v.and_then(|v| v.parse::<u64>().ok());
+        match arg.as_str() {
+            "--iterations" => options.iterations = value(it.next()).unwrap_or(options.iterations as u64) as u32,
+            "--matches" => options.matches = value(it.next()).unwrap_or(options.matches as u64) as u32,
+            "--ticks" => options.ticks = value(it.next()).unwrap_or(options.ticks as u64) as u32,
+            "--seed" => options.seed = value(it.next()).unwrap_or(options.seed),
+            "--output" => if let Some(o) = it.next() { options.output = o.clone() },
+            path => config = Some(load_config(path.to_string())),
+        }
+    }
+    let config = config.unwrap_or_else(Config::default);
+
+    if let Err(e) = tuning::tune(&config, &options) {
+        println!("Error saving the tuned parameters: {}", e);
+    }
+}
+
+/// `env [config] [--ticks N]`
+fn serve_env(args: &[String]) {
+    let mut ticks: u32 = 1500;
+    let mut config = None;
+
+    let mut it = args.iter();
+    while let Some(arg) = it.next() {
+        match arg.as_str() {
+            "--ticks" => ticks = it.next().and_then(|v| v.parse().ok()).unwrap_or(ticks),
+            path => config = Some(load_config(path.to_string())),
+        }
+    }
+    let config = config.unwrap_or_else(Config::default);
+
+    if let Err(e) = env::serve(&config, ticks) {
+        eprintln!("Error in the environment: {}", e);
+    }
+}
+
+/// `bench [config] [--paths N] [--seed N]`
+fn bench(args: &[String]) {
+    let mut options = BenchOptions::default();
+    let mut config = None;
+
+    let mut it = args.iter();
+    while let Some(arg) = it.next() {
+        match arg.as_str() {
+            "--paths" => options.paths = it.next().and_then(|v| v.parse().ok()).unwrap_or(options.paths),
+            "--seed" => options.seed = it.next().and_then(|v| v.parse().ok()).unwrap_or(options.seed),
+            path => config = Some(load_config(path.to_string())),
+        }
+    }
+    let config = config.unwrap_or_else(Config::default);
+
+    bench::bench(&config, &options);
+}
+
+/// `path <map.txt> [config.json] [--to X Y]`
+///
+/// Draws the path of the bot in a map drawn as text: to the block given, or to the frontier, as when exploring
+fn path(args: &[String]) {
+    let mut file = None;
+    let mut config = None;
+    let mut to = None;
+
+    let mut it = args.iter();
+    while let Some(arg) = it.next() {
+        match arg.as_str() {
+            "--to" => {
+                let x = it.next().and_then(|v| v.parse().ok());
+                let y = it.next().and_then(|v| v.parse().ok());
+                to = x.zip(y).map(|(x, y)| Coord { x, y });
+            },
+            path if path.ends_with(".json") => config = Some(load_config(path.to_string())),
+            path => file = Some(path.to_string()),
+        }
+    }
+    let config = config.unwrap_or_else(Config::default);
+
+    let content = match file.map(fs::read_to_string) {
+        Some(Ok(c)) => c,
+        Some(Err(e)) => return println!("Error reading the map: {}", e),
+        None => return println!("Usage: path <map.txt> [config.json] [--to X Y]"),
+    };
+    let m = match text::parse(&content, &config) {
+        Ok(m) => m,
+        Err(e) => return println!("Error in the map: {}", e),
+    };
+    let bot = match m.bot {
+        Some(b) => b,
+        None => return println!("Error in the map: the bot is not in it"),
+    };
+
+    let f = &m.field;
+    let path = match to {
+        Some(t) => distance::from(f, &bot.coord, &bot.dir).path(&t),
+        None => frontier::best_path(f, &bot.coord, &bot.dir, &frontier::clusters(f)).0,
+    };
+    print!("{}", text::render(f, Some(&bot), path.as_ref()));
+    match path {
+        Some(p) => println!("path: {}", p),
+        None => println!("no path"),
+    }
+}
@@ -6,9 +6,11 @@ from typing import Dict
 from connection import Data
 
 SCREEN_SIZE = SCREEN_WIDTH, SCREEN_HEIGHT = 1200, 700
-BLOCK_SIZE = 20
-HORIZONTAL_BORDER = (SCREEN_WIDTH - BLOCK_SIZE * 59) // 2
-VERTICAL_BORDER = (SCREEN_HEIGHT - BLOCK_SIZE * 34) // 2
+MAX_BLOCK_SIZE = 20
+MAP_WIDTH, MAP_HEIGHT = 59, 34     # tamanho padrao, ate o drone informar outro
+BLOCK_SIZE = MAX_BLOCK_SIZE
+HORIZONTAL_BORDER = (SCREEN_WIDTH - BLOCK_SIZE * MAP_WIDTH) // 2
+VERTICAL_BORDER = (SCREEN_HEIGHT - BLOCK_SIZE * MAP_HEIGHT) // 2
 
 COLOR_GREEN = Color(0, 200, 0)
 COLOR_BLUE = Color(100, 100, 255)
@@ -18,6 +20,9 @@ COLOR_WHITE = Color(240, 240, 240)
 COLOR_GRAY = Color(50, 50, 50)
 COLOR_BLACK = Color(0, 0, 0)
 COLOR_ORANGE = Color(255, 100, 0)
+COLOR_PURPLE = Color(150, 50, 200)
+COLOR_LIGHT_RED = Color(240, 130, 130)
+COLOR_LIGHT_PURPLE = Color(200, 160, 230)
 
 
 class PlayerColor:
@@ -33,6 +38,15 @@ class PlayerColor:
 PLAYER_COLOR = PlayerColor()
 
 
+def set_map_size(width: int, height: int):
+    # os blocos diminuem para que mapas maiores caibam na tela
+    global MAP_WIDTH, MAP_HEIGHT, BLOCK_SIZE, HORIZONTAL_BORDER, VERTICAL_BORDER
+    MAP_WIDTH, MAP_HEIGHT = width, height
+    BLOCK_SIZE = max(1, min(MAX_BLOCK_SIZE, SCREEN_WIDTH // width, SCREEN_HEIGHT // height))
+    HORIZONTAL_BORDER = (SCREEN_WIDTH - BLOCK_SIZE * width) // 2
+    VERTICAL_BORDER = (SCREEN_HEIGHT - BLOCK_SIZE * height) // 2
+
+
 def coords_to_pos(x: int, y: int):
     return HORIZONTAL_BORDER + BLOCK_SIZE * x, VERTICAL_BORDER + BLOCK_SIZE * y
 
@@ -42,8 +56,14 @@ def type_to_color(t: str) -> Color:
         return COLOR_GREEN
     elif t == "EMPTY":
         return COLOR_WHITE
-    elif t == "DANGER":
+    elif t == "HOLE":
         return COLOR_RED
+    elif t == "TELEPORT":
+        return COLOR_PURPLE
+    elif t == "SUSPECTED_HOLE":
+        return COLOR_LIGHT_RED
+    elif t == "SUSPECTED_TELEPORT":
+        return COLOR_LIGHT_PURPLE
     elif t == "UNKNOWN":
         return COLOR_GRAY
     elif t == "WALL":
@@ -72,7 +92,21 @@ def id_to_cords(coord_id):
 
 
 def generate_full_unknown_map() -> dict:
-    return {coords_to_id(x, y): type_to_color("UNKNOWN") for x in range(59) for y in range(34)}
+    return {coords_to_id(x, y): type_to_color("UNKNOWN") for x in range(MAP_WIDTH) for y in range(MAP_HEIGHT)}
+
+
+def generate_map_size(d: Data) -> tuple:
+    width, height = 0, 0
+    try:
+        for field in d.field.values():
+            width = max(width, int(field.get('width', 59)))
+            height = max(height, int(field.get('height', 34)))
+    except Exception as e:
+        print("[DISPLAY]: invalid map size: ", e)
+
+    if width <= 0 or height <= 0:
+        return 59, 34
+    return width, height
 
 
 def generate_map(d: Data) -> dict:
@@ -88,8 +122,8 @@ def generate_map(d: Data) -> dict:
 
                 ret[coords_to_id(x, y)] = type_to_color(t)
 
-            midpoint = field.get('midpoint')
-            ret[coords_to_id(int(midpoint[0]), int(midpoint[1]))] = COLOR_ORANGE
+            for frontier in field.get('frontier', []):
+                ret[coords_to_id(int(frontier[0]), int(frontier[1]))] = COLOR_ORANGE
 
     except Exception as e:
         print("[DISPLAY]: invalid map: ", e)
@@ -119,7 +153,8 @@ def generate_player(d: Data) -> dict:
             ret[coords_to_id(x, y)] = (
                 ident, player.get('energy'),
                 player.get('score'), player.get('dir'),
-                player.get('state')
+                player.get('state'), player.get('coverage', 0.0),
+                player.get('trace', '')
             )
 
     except Exception as e:
@@ -139,6 +174,33 @@ def generate_path(d: Data) -> dict:
     return ret
 
 
+def generate_teleports(d: Data) -> list:
+    ret = []
+    try:
+        for field in d.field.values():
+            for teleport in field.get('teleports', []):
+                ret.append(tuple(int(t) for t in teleport))
+
+    except Exception as e:
+        print("[DISPLAY]: invalid teleport info: ", e)
+
+    return ret
+
+
+def generate_enemies(d: Data) -> dict:
+    ret = {}
+    try:
+        for field in d.field.values():
+            for x, y, p in field.get('enemies', []):
+                coords = coords_to_id(int(x), int(y))
+                ret[coords] = max(ret.get(coords, 0.0), float(p))
+
+    except Exception as e:
+        print("[DISPLAY]: invalid enemy info: ", e)
+
+    return ret
+
+
 def draw_map(screen: pygame.Surface, map_info: dict):
     rect = pygame.rect.Rect(0, 0, BLOCK_SIZE, BLOCK_SIZE)
     try:
@@ -203,11 +265,18 @@ def draw_player(screen: pygame.Surface, player_info: dict, font: pygame.font.Fon
             screen.blit(text_score, rect_score)
 
             # state
-            text_state = font.render(f"{player[4]}", True, COLOR_RED)
+            text_state = font.render(f"{player[4]} {player[5]:.0f}%", True, COLOR_RED)
             rect_state = text_state.get_rect()
             rect_state.x, rect_state.y = x + BLOCK_SIZE, y
             screen.blit(text_state, rect_state)
 
+            # why the last action was chosen
+            if player[6]:
+                text_trace = font.render(player[6], True, COLOR_RED)
+                rect_trace = text_trace.get_rect()
+                rect_trace.x, rect_trace.y = x + BLOCK_SIZE, y + BLOCK_SIZE
+                screen.blit(text_trace, rect_trace)
+
     except Exception as e:
         print("[DISPLAY]: error drawing player info: ", e)
 
@@ -229,17 +298,46 @@ def draw_paths(screen: pygame.Surface, paths_info: dict):
         print("[DISPLAY]: error drawing paths: ", e)
 
 
+def draw_teleports(screen: pygame.Surface, teleports_info: list):
+    hbs = BLOCK_SIZE // 2       # HALF_BLOCK_SIZE
+    try:
+        for x0, y0, x1, y1 in teleports_info:
+            x0, y0 = coords_to_pos(x0, y0)
+            x1, y1 = coords_to_pos(x1, y1)
+            pygame.draw.line(screen, COLOR_PURPLE, (x0 + hbs, y0 + hbs), (x1 + hbs, y1 + hbs), width=2)
+
+    except Exception as e:
+        print("[DISPLAY]: error drawing teleports: ", e)
+
+
+def draw_enemies(screen: pygame.Surface, enemies_info: dict):
+    hbs = BLOCK_SIZE // 2       # HALF_BLOCK_SIZE
+    try:
+        for coords_id, p in enemies_info.items():
+            x, y = coords_to_pos(*id_to_cords(coords_id))
+            radius = max(2, int(hbs * min(p, 1.0)))
+            pygame.draw.circle(screen, COLOR_RED, (x + hbs, y + hbs), radius, width=2)
+
+    except Exception as e:
+        print("[DISPLAY]: error drawing enemies: ", e)
+
+
 def draw_all(screen: pygame.Surface, data: Data, font: pygame.font.Font):
     data.block()
+    set_map_size(*generate_map_size(data))
     gen_map = generate_map(data)
     gen_map_info = generate_map_info(data)
     gen_player = generate_player(data)
     gen_path = generate_path(data)
+    gen_teleports = generate_teleports(data)
+    gen_enemies = generate_enemies(data)
     data.unblock()
 
     screen.fill(COLOR_BLACK)
     draw_map(screen, gen_map)
     draw_map_info(screen, gen_map_info, font)
+    draw_teleports(screen, gen_teleports)
+    draw_enemies(screen, gen_enemies)
     draw_paths(screen, gen_path)
     draw_player(screen, gen_player, font)
     pygame.display.flip()